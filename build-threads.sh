@@ -0,0 +1,9 @@
+#!/bin/sh
+
+# Multithreaded wasm build: rayon runs on a web worker pool over shared memory.
+# Needs a nightly toolchain with rust-src, and the page must be served
+# cross-origin isolated (COOP/COEP headers) for SharedArrayBuffer.
+
+RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals' \
+    rustup run nightly \
+    wasm-pack build --target web --release -- --features wasm-threads -Z build-std=panic_abort,std
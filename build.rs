@@ -0,0 +1,19 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    write_c_header();
+}
+
+// write_c_header generates include/eigentrust.h from src/capi.rs.
+#[cfg(feature = "capi")]
+fn write_c_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file("cbindgen.toml").expect("Invalid cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/capi.rs")
+        .generate()
+        .expect("Unable to generate C header")
+        .write_to_file("include/eigentrust.h");
+}
@@ -4,5 +4,13 @@
 
 # cargo build --target wasm32-unknown-unknown --release
 
-wasm-pack build --target web --release
-  
\ No newline at end of file
+# ./build.sh [web|nodejs|bundler]
+# web (the default) goes to pkg/ for index.html and worker.js, other targets to pkg-<target>/.
+target=${1:-web}
+case "$target" in
+    web) out=pkg ;;
+    nodejs|bundler) out=pkg-$target ;;
+    *) echo "Unknown target: $target" >&2; exit 1 ;;
+esac
+
+wasm-pack build --target "$target" --release --out-dir "$out"
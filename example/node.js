@@ -0,0 +1,15 @@
+// Computes scores in Node with the nodejs wasm package:
+//   ./build.sh nodejs && node example/node.js
+const fs = require('fs')
+const path = require('path')
+const { compute, TrustEngine } = require('../pkg-nodejs/eigentrust.js')
+
+// Buffers are Uint8Arrays, so file contents can be passed without decoding.
+const localtrust = fs.readFileSync(path.join(__dirname, 'localtrust2.csv'))
+const pretrust = fs.readFileSync(path.join(__dirname, 'pretrust2.csv'))
+
+console.log(compute(localtrust, pretrust, { alpha: 0.5, top_n: 5 }))
+
+const engine = new TrustEngine(localtrust)
+const { peers, scores } = engine.compute(pretrust, { output: 'arrays' })
+console.log(peers.length, 'peers, top score', scores[0], 'in', engine.timings.total_ms, 'ms')
@@ -0,0 +1,159 @@
+/* Generated by cbindgen from src/capi.rs with `cargo build --features capi`. Do not edit. */
+
+#ifndef EIGENTRUST_H
+#define EIGENTRUST_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum EtDistrust {
+  ET_DISTRUST_IGNORE = 0,
+  ET_DISTRUST_DISCOUNT = 1,
+} EtDistrust;
+
+typedef enum EtDecay {
+  ET_DECAY_NONE = 0,
+  ET_DECAY_EXPONENTIAL = 1,
+  ET_DECAY_LINEAR = 2,
+  ET_DECAY_CUTOFF = 3,
+} EtDecay;
+
+// EtGraph is the opaque handle: the graph, pretrust, options and the
+// scores of the last compute.
+typedef struct EtGraph EtGraph;
+
+// EtOptions mirrors ComputeOptions. Zero epsilon and iteration counts mean
+// the defaults. decay_duration is the half-life, window or maximum age in
+// seconds, and applies to edges added with a timestamp; a zero
+// decay_reference means the newest timestamp.
+typedef struct EtOptions {
+  double alpha;
+  double epsilon;
+  size_t max_iterations;
+  size_t min_iterations;
+  enum EtDistrust distrust;
+  bool prune_unreachable;
+  bool include_unreachable;
+  enum EtDecay decay;
+  double decay_duration;
+  double decay_reference;
+} EtOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct EtOptions et_options_default(void);
+
+// et_graph_new returns an empty graph to be freed with et_graph_free.
+struct EtGraph *et_graph_new(void);
+
+// # Safety
+// `graph` must come from et_graph_new and not be used afterwards.
+void et_graph_free(struct EtGraph *graph);
+
+// # Safety
+// `graph` must be valid. The message is valid until the next call on the graph.
+const char *et_graph_last_error(const struct EtGraph *graph);
+
+// et_graph_add_edge sets the trust level from one peer to another, adding
+// unknown peers. A zero level removes the edge.
+//
+// # Safety
+// `graph` must be valid and the peers NUL-terminated UTF-8.
+int et_graph_add_edge(struct EtGraph *graph, const char *from, const char *to, double level);
+
+// # Safety
+// `graph` must be valid.
+int et_graph_add_edge_id(struct EtGraph *graph, uint64_t from, uint64_t to, double level);
+
+// et_graph_add_edge_at is et_graph_add_edge with the time of the edge in
+// unix seconds, which decay weighs it by. Edges added without one are not
+// decayed.
+//
+// # Safety
+// `graph` must be valid and the peers NUL-terminated UTF-8.
+int et_graph_add_edge_at(struct EtGraph *graph,
+                         const char *from,
+                         const char *to,
+                         double level,
+                         double timestamp);
+
+// # Safety
+// `graph` must be valid.
+int et_graph_add_edge_id_at(struct EtGraph *graph,
+                            uint64_t from,
+                            uint64_t to,
+                            double level,
+                            double timestamp);
+
+// et_graph_set_pretrust sets a peer's pretrust level; zero removes it.
+// Levels are normalized on compute.
+//
+// # Safety
+// `graph` must be valid and `peer` NUL-terminated UTF-8.
+int et_graph_set_pretrust(struct EtGraph *graph, const char *peer, double level);
+
+// # Safety
+// `graph` must be valid.
+int et_graph_set_pretrust_id(struct EtGraph *graph, uint64_t peer, double level);
+
+// # Safety
+// `graph` must be valid and `options` null or valid.
+int et_graph_set_options(struct EtGraph *graph, const struct EtOptions *options);
+
+// et_graph_set_algorithm selects the algorithm by name: eigentrust (the
+// default), pagerank, ppr or sybilrank.
+//
+// # Safety
+// `graph` must be valid and `name` NUL-terminated UTF-8.
+int et_graph_set_algorithm(struct EtGraph *graph, const char *name);
+
+// et_graph_compute runs EigenTrust, keeping the scores sorted by descending score.
+//
+// # Safety
+// `graph` must be valid.
+int et_graph_compute(struct EtGraph *graph);
+
+// # Safety
+// `graph` must be valid.
+size_t et_graph_peer_count(const struct EtGraph *graph);
+
+// # Safety
+// `graph` must be valid.
+size_t et_graph_edge_count(const struct EtGraph *graph);
+
+// et_graph_score_count is the number of scores from the last compute: the
+// peers with a nonzero score, or every peer with include_unreachable. It is
+// zero after the graph or pretrust was edited.
+//
+// # Safety
+// `graph` must be valid.
+size_t et_graph_score_count(const struct EtGraph *graph);
+
+// et_graph_score_at reads the index-th highest score. The peer string is
+// valid until the next compute or edit.
+//
+// # Safety
+// `graph` must be valid; `peer` and `score` may be null.
+int et_graph_score_at(const struct EtGraph *graph, size_t index, const char **peer, double *score);
+
+// et_graph_score reads a peer's score from the last compute, zero for peers
+// without one. It fails for unknown peers and after the graph or pretrust was edited.
+//
+// # Safety
+// `graph` must be valid, `peer` NUL-terminated UTF-8 and `score` valid.
+int et_graph_score(struct EtGraph *graph, const char *peer, double *score);
+
+// # Safety
+// `graph` must be valid and `score` valid.
+int et_graph_score_id(struct EtGraph *graph, uint64_t peer, double *score);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* EIGENTRUST_H */
@@ -0,0 +1,348 @@
+use crate::basic::eigentrust::{
+    compute_transposed, ComputeObserver, ComputeProgress, CANCELLED_ERROR,
+};
+use crate::basic::util::Timer;
+use crate::sparse::entry::Entry;
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// IterationParams are the compute options shared by all algorithms, with
+// the convergence threshold already resolved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationParams {
+    pub alpha: f64,
+    pub epsilon: f64,
+    pub max_iterations: Option<usize>,
+    pub min_iterations: Option<usize>,
+}
+
+// TrustAlgorithm computes global scores from canonicalized local trust and
+// canonicalized seeds (the pretrust). The matrix is passed transposed, so
+// that row i holds the trust peer i receives.
+pub trait TrustAlgorithm: Sync {
+    fn name(&self) -> &'static str;
+
+    // dangling returns where peers without outgoing trust send theirs when
+    // local trust is canonicalized, or None to leave their rows empty.
+    fn dangling(&self, seeds: &Vector) -> Option<Vector> {
+        Some(seeds.clone())
+    }
+
+    // teleport returns the distribution that a walk restarts from with
+    // probability alpha, so that the scores t satisfy
+    // t = alpha * teleport + (1 - alpha) * C^T t. It is None for algorithms
+    // whose scores are not such a fixed point.
+    fn teleport(&self, seeds: &Vector) -> Option<Vector> {
+        Some(seeds.clone())
+    }
+
+    fn compute(
+        &self,
+        local_trust_t: &CSRMatrix,
+        seeds: &Vector,
+        params: &IterationParams,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vector, String>;
+}
+
+// EigenTrust teleports to, and hands dangling trust to, the pretrusted peers.
+pub struct EigenTrust;
+
+impl TrustAlgorithm for EigenTrust {
+    fn name(&self) -> &'static str {
+        "eigentrust"
+    }
+
+    fn compute(
+        &self,
+        local_trust_t: &CSRMatrix,
+        seeds: &Vector,
+        params: &IterationParams,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vector, String> {
+        compute_transposed(
+            local_trust_t,
+            seeds,
+            params.alpha,
+            params.epsilon,
+            params.max_iterations,
+            params.min_iterations,
+            observer,
+        )
+    }
+}
+
+// PageRank ignores the seeds and teleports uniformly, with alpha as the
+// teleport probability (1 - damping factor).
+pub struct PageRank;
+
+fn uniform(dim: usize) -> Vector {
+    let value = 1.0 / dim as f64;
+    Vector::new(dim, (0..dim).map(|index| Entry { index, value }).collect())
+}
+
+impl TrustAlgorithm for PageRank {
+    fn name(&self) -> &'static str {
+        "pagerank"
+    }
+
+    fn dangling(&self, seeds: &Vector) -> Option<Vector> {
+        Some(uniform(seeds.dim))
+    }
+
+    fn teleport(&self, seeds: &Vector) -> Option<Vector> {
+        Some(uniform(seeds.dim))
+    }
+
+    fn compute(
+        &self,
+        local_trust_t: &CSRMatrix,
+        seeds: &Vector,
+        params: &IterationParams,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vector, String> {
+        EigenTrust.compute(local_trust_t, &uniform(seeds.dim), params, observer)
+    }
+}
+
+// PersonalizedPageRank teleports to the seeds. On non-negative local trust
+// this is the same power iteration as EigenTrust; it is kept separate so
+// comparisons can name it.
+pub struct PersonalizedPageRank;
+
+impl TrustAlgorithm for PersonalizedPageRank {
+    fn name(&self) -> &'static str {
+        "ppr"
+    }
+
+    fn compute(
+        &self,
+        local_trust_t: &CSRMatrix,
+        seeds: &Vector,
+        params: &IterationParams,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vector, String> {
+        EigenTrust.compute(local_trust_t, seeds, params, observer)
+    }
+}
+
+// SybilRank (Cao et al. 2012) spreads the seeds' trust along local trust for
+// only O(log n) steps, without teleporting, so that little of it crosses the
+// few edges into a Sybil region. Each peer's trust is then divided by its
+// weighted in-degree. max_iterations overrides the default ceil(log2 n)
+// steps; alpha and epsilon are not used.
+pub struct SybilRank;
+
+impl TrustAlgorithm for SybilRank {
+    fn name(&self) -> &'static str {
+        "sybilrank"
+    }
+
+    // Dangling trust is dropped rather than sent back to the seeds.
+    fn dangling(&self, _seeds: &Vector) -> Option<Vector> {
+        None
+    }
+
+    fn teleport(&self, _seeds: &Vector) -> Option<Vector> {
+        None
+    }
+
+    fn compute(
+        &self,
+        local_trust_t: &CSRMatrix,
+        seeds: &Vector,
+        params: &IterationParams,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vector, String> {
+        let n = local_trust_t.cs_matrix.major_dim;
+        if n == 0 {
+            return Err("Empty local trust matrix".to_string());
+        }
+        if seeds.dim != n {
+            return Err("Dimension mismatch".to_string());
+        }
+
+        let steps = params
+            .max_iterations
+            .unwrap_or_else(|| (n as f64).log2().ceil().max(1.0) as usize);
+        let timer = Timer::start();
+
+        let mut t = seeds.clone();
+        for iteration in 0..steps {
+            let mut next = t.clone();
+            next.mul_vec(local_trust_t, &t)?;
+
+            if let Some(observer) = observer {
+                let mut diff = next.clone();
+                diff.sub_vec(&next, &t)?;
+                let progress = ComputeProgress {
+                    iteration,
+                    delta: diff.norm2(),
+                    elapsed_ms: timer.elapsed_ms(),
+                };
+                if !observer.on_iteration(&progress) {
+                    log::info!("Compute cancelled at iteration={}", iteration);
+                    return Err(CANCELLED_ERROR.to_string());
+                }
+            }
+            t = next;
+        }
+
+        let entries = t
+            .entries
+            .iter()
+            .filter_map(|e| {
+                let degree = local_trust_t.cs_matrix.entries[e.index]
+                    .iter()
+                    .map(|entry| entry.value)
+                    .sum::<f64>();
+                (degree > 0.0).then(|| Entry {
+                    index: e.index,
+                    value: e.value / degree,
+                })
+            })
+            .collect();
+
+        log::info!(
+            "SybilRank finished: dim={} steps={} elapsed={:.3}ms",
+            n,
+            steps,
+            timer.elapsed_ms()
+        );
+        Ok(Vector::new(n, entries))
+    }
+}
+
+// Algorithm selects a TrustAlgorithm by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    EigenTrust,
+    PageRank,
+    #[serde(rename = "ppr")]
+    PersonalizedPageRank,
+    SybilRank,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::EigenTrust,
+        Algorithm::PageRank,
+        Algorithm::PersonalizedPageRank,
+        Algorithm::SybilRank,
+    ];
+
+    pub fn implementation(&self) -> &'static dyn TrustAlgorithm {
+        match self {
+            Algorithm::EigenTrust => &EigenTrust,
+            Algorithm::PageRank => &PageRank,
+            Algorithm::PersonalizedPageRank => &PersonalizedPageRank,
+            Algorithm::SybilRank => &SybilRank,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.implementation().name() == s)
+            .ok_or_else(|| format!("Unknown algorithm: {}", s))
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.implementation().name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // alice -> bob -> carol -> alice, plus dave and erin trusting each other
+    // and a single edge from carol to dave.
+    fn local_trust_t() -> CSRMatrix {
+        let entries = vec![
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (2, 0, 0.5),
+            (2, 3, 0.5),
+            (3, 4, 1.0),
+            (4, 3, 1.0),
+        ];
+        CSRMatrix::new(5, 5, entries).transpose().unwrap()
+    }
+
+    fn params() -> IterationParams {
+        IterationParams {
+            alpha: 0.15,
+            epsilon: 1e-9,
+            max_iterations: None,
+            min_iterations: None,
+        }
+    }
+
+    fn seeds() -> Vector {
+        Vector::new(
+            5,
+            vec![Entry {
+                index: 0,
+                value: 1.0,
+            }],
+        )
+    }
+
+    fn dense(v: &Vector) -> Vec<f64> {
+        let mut values = vec![0.0; v.dim];
+        for e in &v.entries {
+            values[e.index] = e.value;
+        }
+        values
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.to_string().parse::<Algorithm>(), Ok(algorithm));
+            let json = serde_json::to_string(&algorithm).unwrap();
+            assert_eq!(json, format!("\"{}\"", algorithm));
+        }
+        assert!("hits".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_algorithms() {
+        let ct = local_trust_t();
+
+        let eigentrust = dense(&EigenTrust.compute(&ct, &seeds(), &params(), None).unwrap());
+        let ppr = dense(
+            &PersonalizedPageRank
+                .compute(&ct, &seeds(), &params(), None)
+                .unwrap(),
+        );
+        assert_eq!(eigentrust, ppr);
+
+        // Uniform teleport gives the dave-erin cycle more than seeded walks do.
+        let pagerank = dense(&PageRank.compute(&ct, &seeds(), &params(), None).unwrap());
+        assert!((pagerank.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+        assert!(pagerank[3] > eigentrust[3]);
+
+        // Three steps from alice: bob, carol, then half each to alice and dave,
+        // divided by their weighted in-degrees of 0.5 and 1.5.
+        let sybilrank = dense(&SybilRank.compute(&ct, &seeds(), &params(), None).unwrap());
+        let expected = [1.0, 0.0, 0.0, 1.0 / 3.0, 0.0];
+        for (score, expected) in sybilrank.iter().zip(expected) {
+            assert!((score - expected).abs() < 1e-12);
+        }
+    }
+}
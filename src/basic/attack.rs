@@ -0,0 +1,294 @@
+use crate::basic::engine::{ComputeOptions, TrustEngine};
+use crate::basic::localtrust::{decay_local_trust, read_timestamped_local_trust_from_csv};
+use crate::basic::util::strip_headers;
+use crate::graph::generate::Rng;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Attack is a structure of attacker peers joined to an honest graph. Edges
+// from honest peers to attackers are given the level of the honest peer's
+// strongest trust, so that they weigh as much as its real relationships.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attack {
+    // size sybils trusting each other with probability density, trusted by
+    // attack_edges random scored honest peers.
+    Sybil {
+        size: usize,
+        density: f64,
+        attack_edges: usize,
+    },
+    // size colluders fully trusting the next one around a ring, trusted by
+    // attack_edges random scored honest peers.
+    CollusionRing {
+        size: usize,
+        attack_edges: usize,
+    },
+    // A compromised honest peer, the top scored one if not given, trusts
+    // size attackers, which trust each other around a ring.
+    TrustWashing {
+        size: usize,
+        compromised: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttackReport {
+    pub attack: Attack,
+    pub alpha: f64,
+    pub attackers: usize,
+    // Edges from honest peers to attackers.
+    pub attack_edges: usize,
+    // Sum of the attackers' scores; pretrust keeps the total at 1.
+    pub attacker_trust: f64,
+    pub best_attacker_rank: Option<usize>,
+    // How far honest peers scored before the attack moved in the ranking;
+    // peers that lost their score are ranked last.
+    pub mean_rank_displacement: f64,
+    pub max_rank_displacement: usize,
+    pub displaced_peers: usize,
+}
+
+type Edge = (String, String, f64);
+
+// attacker_prefix returns a name prefix that no honest peer starts with.
+fn attacker_prefix(honest: &[String]) -> String {
+    let mut prefix = "attacker".to_string();
+    while honest.iter().any(|name| name.starts_with(&prefix)) {
+        prefix.push('_');
+    }
+    prefix
+}
+
+// ring returns edges of level 1 from each peer to the next, around a ring.
+fn ring(peers: &[String]) -> Vec<Edge> {
+    if peers.len() < 2 {
+        return vec![];
+    }
+    (0..peers.len())
+        .map(|i| (peers[i].clone(), peers[(i + 1) % peers.len()].clone(), 1.0))
+        .collect()
+}
+
+// attack_edges returns the edges the attack adds, and how many of them come
+// from honest peers. Victims are drawn from the peers with a score.
+fn attack_edges(
+    attack: &Attack,
+    honest: &[String],
+    strongest: &HashMap<String, f64>,
+    scores: &[(String, f64)],
+    rng: &mut Rng,
+) -> Result<(Vec<String>, Vec<Edge>, usize), String> {
+    let prefix = attacker_prefix(honest);
+    let attackers =
+        |size: usize| -> Vec<String> { (0..size).map(|i| format!("{}{}", prefix, i)).collect() };
+    let level = |peer: &str| strongest.get(peer).cloned().unwrap_or(1.0);
+
+    let (attackers, edges, count) = match attack {
+        Attack::Sybil {
+            size, attack_edges, ..
+        }
+        | Attack::CollusionRing { size, attack_edges } => {
+            let attackers = attackers(*size);
+            let mut edges = match attack {
+                Attack::Sybil { density, .. } => {
+                    let mut edges = vec![];
+                    for from in &attackers {
+                        for to in &attackers {
+                            if from != to && rng.next_f64() < *density {
+                                edges.push((from.clone(), to.clone(), 1.0 - rng.next_f64()));
+                            }
+                        }
+                    }
+                    edges
+                }
+                _ => ring(&attackers),
+            };
+
+            if scores.is_empty() && *attack_edges > 0 && !attackers.is_empty() {
+                return Err("No scored peers to attack from".to_string());
+            }
+            let count = (*attack_edges).min(scores.len() * attackers.len());
+            let mut added = HashSet::new();
+            while added.len() < count {
+                let from = &scores[rng.below(scores.len())].0;
+                let to = &attackers[rng.below(attackers.len())];
+                if added.insert((from, to)) {
+                    edges.push((from.clone(), to.clone(), level(from)));
+                }
+            }
+            (attackers, edges, count)
+        }
+        Attack::TrustWashing { size, compromised } => {
+            let compromised = match compromised {
+                Some(peer) if honest.contains(peer) => peer.clone(),
+                Some(peer) => return Err(format!("Invalid peer: {}", peer)),
+                None => scores
+                    .first()
+                    .map(|(peer, _)| peer.clone())
+                    .ok_or("No scored peers to compromise")?,
+            };
+            let attackers = attackers(*size);
+            let mut edges = ring(&attackers);
+            for attacker in &attackers {
+                edges.push((compromised.clone(), attacker.clone(), level(&compromised)));
+            }
+            let count = attackers.len();
+            (attackers, edges, count)
+        }
+    };
+    Ok((attackers, edges, count))
+}
+
+fn ranks(scores: &[(String, f64)]) -> HashMap<&str, usize> {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, (peer, _))| (peer.as_str(), i + 1))
+        .collect()
+}
+
+// simulate_attack computes scores for the honest local trust, adds the
+// attack with a seeded random choice of victims, computes again and reports
+// what the attackers gained. Attack edges count as fresh under decay.
+pub fn simulate_attack(
+    localtrust_csv: &str,
+    pretrust_csv: &str,
+    attack: &Attack,
+    options: &ComputeOptions,
+    seed: u64,
+) -> Result<AttackReport, String> {
+    let (local_trust, timestamps, peers) =
+        read_timestamped_local_trust_from_csv(strip_headers(localtrust_csv))?;
+    let honest: Vec<String> = (0..peers.get_max_value())
+        .map(|i| peers.map_reversed[&i].clone())
+        .collect();
+    // Attackers copy the strongest trust as the compute weighs it.
+    let decayed = options
+        .decay
+        .as_ref()
+        .map(|decay| decay_local_trust(&local_trust, &timestamps, decay, options.decay_reference))
+        .transpose()?;
+    let strongest: HashMap<String, f64> = decayed
+        .as_ref()
+        .unwrap_or(&local_trust)
+        .cs_matrix
+        .entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| {
+            let max = row.iter().map(|e| e.value).fold(0.0, f64::max);
+            (max > 0.0).then(|| (honest[i].clone(), max))
+        })
+        .collect();
+
+    let mut engine = TrustEngine::with_timestamped_graph(local_trust, timestamps, peers);
+    let before = engine.compute(pretrust_csv, options)?;
+
+    let mut rng = Rng::new(seed);
+    let (attackers, edges, count) = attack_edges(attack, &honest, &strongest, &before, &mut rng)?;
+    for (from, to, level) in &edges {
+        engine.set_edge(from, to, *level)?;
+    }
+    let after = engine.compute(pretrust_csv, options)?;
+
+    let attacker_set: HashSet<&str> = attackers.iter().map(|a| a.as_str()).collect();
+    let ranks_after = ranks(&after);
+    let displacements: Vec<usize> = before
+        .iter()
+        .enumerate()
+        .map(|(i, (peer, _))| {
+            let rank = ranks_after
+                .get(peer.as_str())
+                .cloned()
+                .unwrap_or(after.len() + 1);
+            rank.abs_diff(i + 1)
+        })
+        .collect();
+
+    Ok(AttackReport {
+        attack: attack.clone(),
+        alpha: options.alpha,
+        attackers: attackers.len(),
+        attack_edges: count,
+        attacker_trust: after
+            .iter()
+            .filter(|(peer, _)| attacker_set.contains(peer.as_str()))
+            .map(|(_, score)| score)
+            .sum(),
+        best_attacker_rank: after
+            .iter()
+            .position(|(peer, _)| attacker_set.contains(peer.as_str()))
+            .map(|i| i + 1),
+        mean_rank_displacement: if displacements.is_empty() {
+            0.0
+        } else {
+            displacements.iter().sum::<usize>() as f64 / displacements.len() as f64
+        },
+        max_rank_displacement: displacements.iter().cloned().max().unwrap_or(0),
+        displaced_peers: displacements.iter().filter(|&&d| d > 0).count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::generate::SyntheticGraph;
+
+    #[test]
+    fn test_simulate_attack() {
+        let mut graph = SyntheticGraph::new(5);
+        let honest = graph.barabasi_albert(300, 3);
+        let localtrust_csv = graph.to_csv();
+        let pretrust_csv = graph.pretrust_csv(honest.start..honest.start + 5);
+
+        let sybil = Attack::Sybil {
+            size: 100,
+            density: 0.1,
+            attack_edges: 5,
+        };
+        let report = |attack: &Attack, alpha: f64| {
+            let options = ComputeOptions {
+                alpha,
+                ..ComputeOptions::default()
+            };
+            simulate_attack(&localtrust_csv, &pretrust_csv, attack, &options, 1).unwrap()
+        };
+
+        let weak = report(&sybil, 0.1);
+        assert_eq!(weak.attackers, 100);
+        assert_eq!(weak.attack_edges, 5);
+        assert!(weak.attacker_trust > 0.0);
+        assert!(weak.displaced_peers > 0);
+        assert_eq!(weak, report(&sybil, 0.1));
+        // More teleport to the seeds leaves less for the sybils.
+        assert!(report(&sybil, 0.5).attacker_trust < weak.attacker_trust);
+
+        let ring = report(
+            &Attack::CollusionRing {
+                size: 3,
+                attack_edges: 1,
+            },
+            0.1,
+        );
+        let washing = report(
+            &Attack::TrustWashing {
+                size: 3,
+                compromised: None,
+            },
+            0.1,
+        );
+        assert_eq!(washing.attack_edges, 3);
+        // Compromising the top peer is worth more than a random victim.
+        assert!(washing.attacker_trust > ring.attacker_trust);
+        assert!(washing.best_attacker_rank < ring.best_attacker_rank);
+
+        let options = ComputeOptions::default();
+        let unknown = Attack::TrustWashing {
+            size: 3,
+            compromised: Some("nobody".to_string()),
+        };
+        assert!(simulate_attack(&localtrust_csv, &pretrust_csv, &unknown, &options, 1).is_err());
+    }
+}
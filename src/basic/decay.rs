@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Decay weighs local trust by the age of the interaction it records, so that
+// old trust counts less. Ages and durations are in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Decay {
+    // Trust halves every half_life.
+    Exponential { half_life: f64 },
+    // Trust falls linearly to zero over window.
+    Linear { window: f64 },
+    // Trust older than max_age is dropped.
+    Cutoff { max_age: f64 },
+}
+
+impl Decay {
+    // factor is the weight of trust of the given age. Trust from after the
+    // reference time counts fully.
+    pub fn factor(&self, age: f64) -> f64 {
+        let age = age.max(0.0);
+        match *self {
+            Decay::Exponential { half_life } => 0.5f64.powf(age / half_life),
+            Decay::Linear { window } => (1.0 - age / window).max(0.0),
+            Decay::Cutoff { max_age } => {
+                if age <= max_age {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let duration = match *self {
+            Decay::Exponential { half_life } => half_life,
+            Decay::Linear { window } => window,
+            Decay::Cutoff { max_age } => max_age,
+        };
+        if duration.is_finite() && duration > 0.0 {
+            Ok(())
+        } else {
+            Err(format!("Decay duration must be positive: {}", duration))
+        }
+    }
+}
+
+// parse_duration reads seconds, optionally followed by a one letter unit: s,
+// m, h, d, w or y (365 days), e.g. 90d. Only the last letter is a unit, so
+// that exponents like 1e5 are read as numbers.
+pub fn parse_duration(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => s.split_at(i),
+        _ => (s, "s"),
+    };
+    let seconds = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        "w" => 7.0 * 86400.0,
+        "y" => 365.0 * 86400.0,
+        _ => return Err(format!("Unknown duration unit: {}", unit)),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .map(|n| n * seconds)
+        .ok_or_else(|| format!("Invalid duration: {}", s))
+}
+
+// Decay is written model:duration, e.g. exponential:30d, linear:1y or
+// cutoff:90d.
+impl FromStr for Decay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (model, duration) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected model:duration, e.g. exponential:30d: {}", s))?;
+        let duration = parse_duration(duration)?;
+        let decay = match model.to_ascii_lowercase().as_str() {
+            "exponential" => Decay::Exponential {
+                half_life: duration,
+            },
+            "linear" => Decay::Linear { window: duration },
+            "cutoff" => Decay::Cutoff { max_age: duration },
+            _ => return Err(format!("Unknown decay model: {}", model)),
+        };
+        decay.validate()?;
+        Ok(decay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay() {
+        let day = 86400.0;
+        let exponential: Decay = "exponential:30d".parse().unwrap();
+        assert_eq!(
+            exponential,
+            Decay::Exponential {
+                half_life: 30.0 * day
+            }
+        );
+        assert_eq!(exponential.factor(0.0), 1.0);
+        assert!((exponential.factor(60.0 * day) - 0.25).abs() < 1e-12);
+        // Timestamps after the reference time are not boosted.
+        assert_eq!(exponential.factor(-day), 1.0);
+
+        let linear: Decay = "linear:10d".parse().unwrap();
+        assert!((linear.factor(2.5 * day) - 0.75).abs() < 1e-12);
+        assert_eq!(linear.factor(20.0 * day), 0.0);
+
+        let cutoff: Decay = "cutoff:3600".parse().unwrap();
+        assert_eq!(cutoff.factor(3600.0), 1.0);
+        assert_eq!(cutoff.factor(3601.0), 0.0);
+
+        assert_eq!(parse_duration("2w").unwrap(), 14.0 * day);
+        assert_eq!(parse_duration("1e5").unwrap(), 1e5);
+        assert_eq!(parse_duration("1.5e1d").unwrap(), 15.0 * day);
+        assert!(parse_duration("90days").is_err());
+        assert!(parse_duration("infs").is_err());
+        assert!(parse_duration("1e").is_err());
+        assert!("exponential".parse::<Decay>().is_err());
+        assert!("exponential:0d".parse::<Decay>().is_err());
+        assert!("step:1d".parse::<Decay>().is_err());
+        assert!("linear:1x".parse::<Decay>().is_err());
+    }
+}
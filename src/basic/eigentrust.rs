@@ -1,12 +1,10 @@
-use super::util::current_time_millis;
-use super::util::PeersMap;
+use super::util::Timer;
 use crate::sparse::entry::Entry;
-use crate::sparse::matrix::{CSMatrix, CSRMatrix};
+use crate::sparse::matrix::CSRMatrix;
 use crate::sparse::vector::Vector;
 use std::cmp;
-use std::collections::HashMap;
-use std::error::Error;
 use std::f64;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
 // Canonicalize scales sparse entries in-place so that their values sum to one.
 // If entries sum to zero, Canonicalize returns an error indicating a zero-sum vector.
@@ -93,7 +91,11 @@ impl FlatTailChecker {
                 .partial_cmp(&a.value)
                 .unwrap_or(cmp::Ordering::Equal)
         });
-        let ranking: Vec<usize> = entries.iter().map(|entry| entry.index).collect();
+        let ranking: Vec<usize> = entries
+            .iter()
+            .take(self.num_leaders)
+            .map(|entry| entry.index)
+            .collect();
 
         if ranking == self.stats.ranking {
             self.stats.length += 1;
@@ -119,21 +121,115 @@ pub struct FlatTailStats {
     pub ranking: Vec<usize>,
 }
 
+// ComputeProgress describes one finished iteration of compute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComputeProgress {
+    pub iteration: usize,
+    // Convergence delta as of this iteration; the initial sentinel until checked.
+    pub delta: f64,
+    pub elapsed_ms: f64,
+}
+
+// ComputeObserver is notified after every iteration of compute and can stop
+// it by returning false, in which case compute fails with CANCELLED_ERROR.
+pub trait ComputeObserver {
+    fn on_iteration(&self, progress: &ComputeProgress) -> bool;
+}
+
+impl<F> ComputeObserver for F
+where
+    F: Fn(&ComputeProgress) -> bool,
+{
+    fn on_iteration(&self, progress: &ComputeProgress) -> bool {
+        self(progress)
+    }
+}
+
+// ComputeControl is an observer shared with a running compute so that other
+// threads can follow its progress and cancel it.
+#[derive(Debug, Default)]
+pub struct ComputeControl {
+    cancelled: AtomicBool,
+    iteration: AtomicUsize,
+    // f64 bits of the last convergence delta.
+    delta: AtomicU64,
+}
+
+impl ComputeControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn iteration(&self) -> usize {
+        self.iteration.load(Ordering::Relaxed)
+    }
+
+    pub fn delta(&self) -> f64 {
+        f64::from_bits(self.delta.load(Ordering::Relaxed))
+    }
+}
+
+impl ComputeObserver for ComputeControl {
+    fn on_iteration(&self, progress: &ComputeProgress) -> bool {
+        self.iteration.store(progress.iteration, Ordering::Relaxed);
+        self.delta.store(progress.delta.to_bits(), Ordering::Relaxed);
+        !self.is_cancelled()
+    }
+}
+
+pub const CANCELLED_ERROR: &str = "Compute cancelled";
+
 // Compute function implements the EigenTrust algorithm.
 // todo Error instead of String
-pub fn compute<'a>(
-    mut c: &CSRMatrix,
-    mut p: &Vector,
+pub fn compute(
+    c: &CSRMatrix,
+    p: &Vector,
+    a: f64,
+    e: f64,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+) -> Result<Vector, String> {
+    compute_with_observer(c, p, a, e, max_iterations, min_iterations, None)
+}
+
+// compute_with_observer is compute reporting each iteration to the observer.
+pub fn compute_with_observer(
+    c: &CSRMatrix,
+    p: &Vector,
+    a: f64,
+    e: f64,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<Vector, String> {
+    let ct = c.transpose()?;
+    compute_transposed(&ct, p, a, e, max_iterations, min_iterations, observer)
+}
+
+// compute_transposed is compute_with_observer taking the transposed local trust
+// matrix, so that callers can time or reuse the transpose.
+pub fn compute_transposed(
+    ct: &CSRMatrix,
+    p: &Vector,
     a: f64,
     e: f64,
     max_iterations: Option<usize>,
     min_iterations: Option<usize>,
+    observer: Option<&dyn ComputeObserver>,
 ) -> Result<Vector, String> {
     if a.is_nan() {
         return Err("Error: alpha cannot be NaN".to_string());
     }
 
-    let n = c.cs_matrix.major_dim;
+    let n = ct.cs_matrix.major_dim;
     if n == 0 {
         return Err("Empty local trust matrix".to_string());
     }
@@ -142,18 +238,16 @@ pub fn compute<'a>(
         return Err("Dimension mismatch".to_string());
     }
 
-    log::debug!("{:?}",p.sum());
+    log::debug!("pretrust sum={}", p.sum());
 
     let check_freq = 1;
-    let min_iters = check_freq;
 
-    let t0 = current_time_millis();
+    let timer = Timer::start();
 
     let mut t1 = p.clone();
-    let ct = c.transpose()?;
 
     let mut ap = p.clone();
-    ap.scale_vec(a, p);
+    ap.scale_vec(a, p)?;
 
     let num_leaders = n;
 
@@ -177,31 +271,43 @@ pub fn compute<'a>(
     );
 
     while iter < max_iters {
-        let iter_t0 = current_time_millis();
+        let iter_timer = Timer::start();
 
-        println!("iter {:?}", iter);
-        println!("d {:?}", conv_checker.delta());
-        println!("conv_checker.converged() {:?}", conv_checker.converged());
+        if iter >= min_iters && iter.saturating_sub(min_iters) % check_freq == 0 {
+            conv_checker.update(&t1)?;
 
-        if iter.saturating_sub(min_iters) % check_freq == 0 {
-            if iter >= min_iters {
-                conv_checker.update(&t1);
+            flat_tail_checker.update(&t1, conv_checker.delta());
 
-                flat_tail_checker.update(&t1, conv_checker.delta());
-
-                if conv_checker.converged() && flat_tail_checker.reached() {
-                    break;
-                }
+            if conv_checker.converged() && flat_tail_checker.reached() {
+                break;
             }
         }
 
         let mut new_t1 = t1.clone();
-        new_t1.mul_vec(&ct, &t1)?;
+        new_t1.mul_vec(ct, &t1)?;
         let mut t2 = new_t1.clone();
-        t2.scale_vec(1.0 - a, &new_t1);
+        t2.scale_vec(1.0 - a, &new_t1)?;
         t1.add_vec(&t2, &ap)?;
 
-        let iter_t1 = current_time_millis();
+        log::trace!(
+            "iteration={} delta={} converged={} took={:.3}ms",
+            iter,
+            conv_checker.delta(),
+            conv_checker.converged(),
+            iter_timer.elapsed_ms()
+        );
+
+        if let Some(observer) = observer {
+            let progress = ComputeProgress {
+                iteration: iter,
+                delta: conv_checker.delta(),
+                elapsed_ms: timer.elapsed_ms(),
+            };
+            if !observer.on_iteration(&progress) {
+                log::info!("Compute cancelled at iteration={}", iter);
+                return Err(CANCELLED_ERROR.to_string());
+            }
+        }
 
         iter += 1;
     }
@@ -210,17 +316,16 @@ pub fn compute<'a>(
         return Err("Reached maximum iterations without convergence".to_string());
     }
 
-    let t1_time = current_time_millis();
-
     log::info!(
-        "finished: alpha={} dim={} nnz={} epsilon={} flatTail={} iterations={} numLeaders={}",
+        "finished: alpha={} dim={} nnz={} epsilon={} flatTail={} iterations={} numLeaders={} elapsed={:.3}ms",
         a,
-        n, 
-        ct.cs_matrix.nnz(), 
-        e, 
-        flat_tail, 
-        iter, 
-        num_leaders, 
+        n,
+        ct.cs_matrix.nnz(),
+        e,
+        flat_tail,
+        iter,
+        num_leaders,
+        timer.elapsed_ms(),
     );
 
     Ok(t1)
@@ -255,7 +360,7 @@ pub fn discount_trust_vector(t: &mut Vector, discounts: &CSRMatrix) -> Result<()
                     dim: t.dim,
                     entries: distrusts.clone(),
                 }),
-            );
+            )?;
             temp_vec
         };
 
@@ -270,9 +375,7 @@ pub fn discount_trust_vector(t: &mut Vector, discounts: &CSRMatrix) -> Result<()
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::sparse::entry::Entry;
-    use crate::sparse::matrix::CSRMatrix;
-    use crate::sparse::vector::Vector;
+    use crate::sparse::matrix::CSMatrix;
 
     #[test]
     fn test_discount_trust_vector() {
@@ -602,4 +705,29 @@ mod tests {
         let result = compute(&c, &p, a, e, None, None).unwrap();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_compute_with_observer() {
+        let c = CSRMatrix::new(2, 2, vec![(0, 1, 1.0), (1, 0, 1.0)]);
+        let p = Vector::new(2, vec![Entry::new(0, 1.0)]);
+
+        let control = ComputeControl::new();
+        let result = compute_with_observer(&c, &p, 0.5, 1e-9, None, None, Some(&control));
+        assert!(result.is_ok());
+        assert!(control.iteration() > 0);
+        assert!(control.delta() < 1e-6);
+
+        control.cancel();
+        let result = compute_with_observer(&c, &p, 0.5, 1e-9, None, None, Some(&control));
+        assert_eq!(result, Err(CANCELLED_ERROR.to_string()));
+
+        let seen = std::cell::RefCell::new(vec![]);
+        let stop_at_third = |progress: &ComputeProgress| {
+            seen.borrow_mut().push(progress.iteration);
+            progress.iteration < 2
+        };
+        let result = compute_with_observer(&c, &p, 0.5, 1e-9, None, None, Some(&stop_at_third));
+        assert_eq!(result, Err(CANCELLED_ERROR.to_string()));
+        assert_eq!(seen.into_inner(), vec![0, 1, 2]);
+    }
 }
@@ -1,43 +1,269 @@
-use super::util::strip_headers;
-use crate::basic::eigentrust::compute;
-use crate::basic::eigentrust::discount_trust_vector;
+use crate::basic::algorithm::{Algorithm, IterationParams};
+use crate::basic::decay::Decay;
+use crate::basic::eigentrust::{discount_trust_vector, ComputeObserver};
+use crate::basic::explain::{explain_scores, Explanation};
 use crate::basic::localtrust::{
-    canonicalize_local_trust,
-    extract_distrust,
-    read_local_trust_from_csv,
+    canonicalize_local_trust, decay_local_trust, extract_distrust,
+    read_local_trust_from_csv_with_decay, read_timestamped_local_trust_from_csv,
+    timestamped_local_trust, EdgeTimestamps,
 };
-use crate::basic::trustvector::canonicalize_trust_vector;
-use crate::basic::trustvector::read_trust_vector_from_csv;
+use crate::basic::trustvector::{canonicalize_trust_vector, read_trust_vector_from_csv};
+use crate::basic::util::{strip_headers, PeersMap, Timer};
+use crate::graph::collusion::{detect_collusion, CollusionOptions, SuspiciousGroup};
+use crate::graph::community::{louvain, Communities, CommunityScores};
+use crate::graph::paths::{name_paths, top_trust_paths, NamedTrustPath};
+use crate::graph::reachability::{
+    reachability, reachable_from, restore_entries, restore_peers, select_entries, select_peers,
+    Reachability,
+};
+use crate::graph::stats::{graph_stats, GraphStats};
 use crate::sparse::entry::Entry;
-use crate::sparse::matrix::{ CSMatrix, CSRMatrix };
+use crate::sparse::matrix::CSRMatrix;
 use crate::sparse::vector::Vector;
+
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::f64::INFINITY;
-use std::fs;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
 
 // todo array inputs
 
+// DistrustPolicy controls what happens to negative local trust values.
+// Ignore drops them; Discount subtracts each distruster's global trust,
+// spread over its canonicalized distrust row, from the final scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistrustPolicy {
+    Ignore,
+    Discount,
+}
+
+impl FromStr for DistrustPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ignore" => Ok(DistrustPolicy::Ignore),
+            "discount" => Ok(DistrustPolicy::Discount),
+            _ => Err(format!("Unknown distrust policy: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for DistrustPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistrustPolicy::Ignore => write!(f, "ignore"),
+            DistrustPolicy::Discount => write!(f, "discount"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ComputeOptions {
+    pub alpha: f64,
+    // Convergence threshold; defaults to 1e-6 divided by the number of peers.
+    pub epsilon: Option<f64>,
+    pub max_iterations: Option<usize>,
+    pub min_iterations: Option<usize>,
+    pub distrust: DistrustPolicy,
+    pub algorithm: Algorithm,
+    // Drop peers the pretrust cannot reach before computing; their scores
+    // are zero either way.
+    pub prune_unreachable: bool,
+    // List peers without a score with score zero instead of leaving them out.
+    pub include_unreachable: bool,
+    // Weigh local trust by the age of its timestamp column. TrustEngine keeps
+    // the timestamps of its graph to apply it on each compute; a bare matrix
+    // has none, so calculate_from_matrix rejects it.
+    pub decay: Option<Decay>,
+    // Time that ages are measured from, in unix seconds; the newest timestamp
+    // if not given.
+    pub decay_reference: Option<f64>,
+}
+
+impl Default for ComputeOptions {
+    fn default() -> Self {
+        ComputeOptions {
+            alpha: 0.5,
+            epsilon: None,
+            max_iterations: None,
+            min_iterations: None,
+            distrust: DistrustPolicy::Ignore,
+            algorithm: Algorithm::EigenTrust,
+            prune_unreachable: false,
+            include_unreachable: false,
+            decay: None,
+            decay_reference: None,
+        }
+    }
+}
+
 pub fn calculate_from_csv(
     localtrust_csv: &str,
     pretrust_csv: &str,
     alpha: Option<f64>
 ) -> Result<Vec<(String, f64)>, String> {
-    log::info!("Compute starting...");
+    let options = ComputeOptions {
+        alpha: alpha.unwrap_or(0.5),
+        ..ComputeOptions::default()
+    };
+    calculate_from_csv_with_options(localtrust_csv, pretrust_csv, &options)
+}
+
+pub fn calculate_from_csv_with_options(
+    localtrust_csv: &str,
+    pretrust_csv: &str,
+    options: &ComputeOptions,
+) -> Result<Vec<(String, f64)>, String> {
+    calculate_from_csv_with_observer(localtrust_csv, pretrust_csv, options, None)
+}
 
-    let a = alpha.unwrap_or(0.5);
+// calculate_from_csv_with_observer reports each compute iteration to the observer,
+// which can also stop the compute.
+pub fn calculate_from_csv_with_observer(
+    localtrust_csv: &str,
+    pretrust_csv: &str,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<Vec<(String, f64)>, String> {
+    calculate_from_csv_with_timings(localtrust_csv, pretrust_csv, options, observer)
+        .map(|(entries, _)| entries)
+}
+
+// PhaseTimings is the wall-clock time spent in each step of a compute.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PhaseTimings {
+    pub parse_ms: f64,
+    pub canonicalize_ms: f64,
+    pub transpose_ms: f64,
+    pub iterate_ms: f64,
+    pub discount_ms: f64,
+    pub sort_ms: f64,
+    pub total_ms: f64,
+}
+
+// calculate_from_csv_with_timings is calculate_from_csv_with_observer also
+// returning how long each phase took.
+pub fn calculate_from_csv_with_timings(
+    localtrust_csv: &str,
+    pretrust_csv: &str,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<(Vec<(String, f64)>, PhaseTimings), String> {
+    log::info!("Compute starting...");
+    let timer = Timer::start();
 
     let localtrust_csv = strip_headers(localtrust_csv);
+    let (local_trust, peers) = read_local_trust_from_csv_with_decay(
+        localtrust_csv,
+        options.decay.as_ref(),
+        options.decay_reference,
+    )?;
+    let parse_ms = timer.elapsed_ms();
+
+    let (entries, result) = calculate(local_trust, &peers, pretrust_csv, options, observer)?;
+    let mut timings = result.timings;
+    timings.parse_ms += parse_ms;
+    timings.total_ms += parse_ms;
+    log::debug!("timings: {:?}", timings);
+
+    Ok((entries, timings))
+}
+
+// calculate runs the compute pipeline on parsed, not yet canonicalized local trust.
+fn calculate(
+    local_trust: CSRMatrix,
+    peers: &PeersMap,
+    pretrust_csv: &str,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<(Vec<(String, f64)>, MatrixCompute), String> {
+    let timer = Timer::start();
+
     let pretrust_csv = strip_headers(pretrust_csv);
 
-    let (mut local_trust, peers) = read_local_trust_from_csv(&localtrust_csv).unwrap();
+    let peer_indices = &peers.map;
+
+    let pre_trust = read_trust_vector_from_csv(pretrust_csv, peer_indices)?;
+    let parse_ms = timer.elapsed_ms();
 
-    let mut peer_indices = peers.map;
+    let (entries, mut result) = calculate_named(local_trust, peers, pre_trust, options, observer)?;
+    result.timings.parse_ms += parse_ms;
+    result.timings.total_ms += parse_ms;
 
-    let mut pre_trust = read_trust_vector_from_csv(pretrust_csv, &peer_indices).unwrap();
+    Ok((entries, result))
+}
 
-    let c_dim = local_trust.cs_matrix.dim().unwrap();
+// calculate_named is compute_matrix also returning scores by peer name.
+fn calculate_named(
+    local_trust: CSRMatrix,
+    peers: &PeersMap,
+    pre_trust: Vector,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<(Vec<(String, f64)>, MatrixCompute), String> {
+    let mut result = compute_matrix(local_trust, pre_trust, options, observer)?;
 
-    let e = 1e-6 / (c_dim as f64);
+    let timer = Timer::start();
+    let entries = named_scores(&result.scores, peers, options.include_unreachable);
+    result.timings.sort_ms = timer.elapsed_ms();
+    result.timings.total_ms += result.timings.sort_ms;
+
+    Ok((entries, result))
+}
+
+// MatrixCompute holds the scores along with the canonicalized inputs they
+// were computed from, which TrustEngine keeps to explain them.
+struct MatrixCompute {
+    scores: Vector,
+    // Scores before the distrust discount.
+    trust: Vector,
+    local_trust_t: CSRMatrix,
+    pre_trust: Vector,
+    teleport: Option<Vector>,
+    alpha: f64,
+    decay: Option<Decay>,
+    decay_reference: Option<f64>,
+    timings: PhaseTimings,
+}
+
+// calculate_from_matrix runs the compute pipeline on a local trust matrix and
+// pretrust vector, both not yet canonicalized, returning scores indexed like
+// the matrix. Inputs of different dimensions are padded to the larger one.
+// The matrix has no timestamps, so options.decay is an error; decay it with
+// decay_local_trust first.
+pub fn calculate_from_matrix(
+    local_trust: CSRMatrix,
+    pre_trust: Vector,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<(Vector, PhaseTimings), String> {
+    if options.decay.is_some() {
+        return Err("Decay needs timestamps, which a local trust matrix does not have".to_string());
+    }
+    let result = compute_matrix(local_trust, pre_trust, options, observer)?;
+    Ok((result.scores, result.timings))
+}
+
+fn compute_matrix(
+    mut local_trust: CSRMatrix,
+    mut pre_trust: Vector,
+    options: &ComputeOptions,
+    observer: Option<&dyn ComputeObserver>,
+) -> Result<MatrixCompute, String> {
+    let total = Timer::start();
+    let mut timer = Timer::start();
+    let mut timings = PhaseTimings::default();
+
+    let a = options.alpha;
+    let algorithm = options.algorithm.implementation();
+
+    let c_dim = local_trust.cs_matrix.dim()?;
+
+    let e = options.epsilon.unwrap_or(1e-6 / (c_dim as f64));
 
     let p_dim = pre_trust.dim;
     if c_dim < p_dim {
@@ -48,16 +274,87 @@ pub fn calculate_from_csv(
 
     canonicalize_trust_vector(&mut pre_trust);
 
-    let mut discounts = extract_distrust(&mut local_trust).unwrap();
+    let mut discounts = extract_distrust(&mut local_trust)?;
 
-    canonicalize_local_trust(&mut local_trust, Some(pre_trust.clone())).unwrap();
-    canonicalize_local_trust(&mut discounts, None).unwrap();
+    // Dangling trust only flows to the seeds, so reachability on the positive
+    // local trust is the same as on the canonicalized matrix.
+    let dim = pre_trust.dim;
+    let teleport = algorithm.teleport(&pre_trust);
+    let mut kept = None;
+    if options.prune_unreachable {
+        let seeds: Vec<usize> = teleport
+            .as_ref()
+            .unwrap_or(&pre_trust)
+            .entries
+            .iter()
+            .filter(|e| e.value > 0.0)
+            .map(|e| e.index)
+            .collect();
+        let reachable = reachable_from(&local_trust, &seeds);
+        let keep: Vec<usize> = (0..dim).filter(|&i| reachable[i]).collect();
+        if keep.len() < dim {
+            log::info!("Pruning {} unreachable peers of {}", dim - keep.len(), dim);
+            local_trust = select_peers(&local_trust, &keep);
+            kept = Some(keep);
+        }
+    }
+    let seeds = match &kept {
+        Some(keep) => select_entries(&pre_trust, keep),
+        None => pre_trust.clone(),
+    };
+
+    canonicalize_local_trust(&mut local_trust, algorithm.dangling(&seeds))?;
+    canonicalize_local_trust(&mut discounts, None)?;
+    timings.canonicalize_ms = timer.lap();
+
+    let local_trust_t = local_trust.transpose()?;
+    timings.transpose_ms = timer.lap();
 
-    let mut trust_scores = compute(&local_trust, &pre_trust, a, e, None, None).unwrap();
+    let params = IterationParams {
+        alpha: a,
+        epsilon: e,
+        max_iterations: options.max_iterations,
+        min_iterations: options.min_iterations,
+    };
+    let mut trust_scores = algorithm.compute(&local_trust_t, &seeds, &params, observer)?;
+    timings.iterate_ms = timer.lap();
 
-    let mut trust_scores2 = trust_scores.clone();
-    discount_trust_vector(&mut trust_scores2, &discounts)?;
+    let local_trust_t = match &kept {
+        Some(keep) => {
+            trust_scores = restore_entries(&trust_scores, keep, dim);
+            restore_peers(&local_trust_t, keep, dim)
+        }
+        None => local_trust_t,
+    };
 
+    let trust = trust_scores.clone();
+    if options.distrust == DistrustPolicy::Discount {
+        discount_trust_vector(&mut trust_scores, &discounts)?;
+    }
+    timings.discount_ms = timer.lap();
+    timings.total_ms = total.elapsed_ms();
+
+    Ok(MatrixCompute {
+        scores: trust_scores,
+        trust,
+        local_trust_t,
+        teleport,
+        pre_trust,
+        alpha: a,
+        decay: options.decay,
+        decay_reference: options.decay_reference,
+        timings,
+    })
+}
+
+// named_scores pairs scores with peer names, sorted by descending score.
+// With include_unreachable, peers without a score follow with score zero,
+// in the order they were first seen.
+fn named_scores(
+    trust_scores: &Vector,
+    peers: &PeersMap,
+    include_unreachable: bool,
+) -> Vec<(String, f64)> {
     let mut entries = vec![];
 
     for e in &trust_scores.entries {
@@ -66,14 +363,477 @@ pub fn calculate_from_csv(
         entries.push((name, e.value));
     }
 
-    entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    if include_unreachable {
+        let mut scored = vec![false; trust_scores.dim.max(peers.get_max_value())];
+        for e in &trust_scores.entries {
+            scored[e.index] = true;
+        }
+        for (index, _) in scored.iter().enumerate().filter(|(_, &scored)| !scored) {
+            entries.push((peers.map_reversed[&index].clone(), 0.0));
+        }
+    }
+
+    entries
+}
 
-    Ok(entries)
+// TrustEngine keeps parsed local trust between computes, so that pretrust and
+// options can change without re-reading the graph. Edges can be edited in
+// place, and the scores of the last compute are kept for peer lookups.
+// Peers are never forgotten: removing all edges of a peer leaves it in the graph.
+// Edge timestamps are kept, so that each compute can apply its own decay.
+pub struct TrustEngine {
+    local_trust: CSRMatrix,
+    timestamps: EdgeTimestamps,
+    peers: PeersMap,
+    scores: Option<HashMap<String, f64>>,
+    timings: Option<PhaseTimings>,
+    last: Option<MatrixCompute>,
+}
+
+impl Default for TrustEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrustEngine {
+    // new returns an engine with an empty graph, to be filled with set_edge.
+    pub fn new() -> Self {
+        Self::with_graph(CSRMatrix::new(0, 0, vec![]), PeersMap::new())
+    }
+
+    // with_graph takes parsed local trust, e.g. from read_local_trust_from_csv.
+    pub fn with_graph(local_trust: CSRMatrix, peers: PeersMap) -> Self {
+        let timestamps = local_trust
+            .cs_matrix
+            .entries
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect();
+        Self::with_timestamped_graph(local_trust, timestamps, peers)
+    }
+
+    // with_timestamped_graph takes parsed local trust with its timestamps,
+    // e.g. from read_timestamped_local_trust_from_csv.
+    pub fn with_timestamped_graph(
+        local_trust: CSRMatrix,
+        timestamps: EdgeTimestamps,
+        peers: PeersMap,
+    ) -> Self {
+        TrustEngine {
+            local_trust,
+            timestamps,
+            peers,
+            scores: None,
+            timings: None,
+            last: None,
+        }
+    }
+
+    pub fn from_csv(localtrust_csv: &str) -> Result<Self, String> {
+        let (local_trust, timestamps, peers) =
+            read_timestamped_local_trust_from_csv(strip_headers(localtrust_csv))?;
+        Ok(Self::with_timestamped_graph(local_trust, timestamps, peers))
+    }
+
+    // from_edges builds the graph from (truster, trustee, level) triples.
+    pub fn from_edges<I, S>(edges: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (S, S, f64)>,
+        S: AsRef<str>,
+    {
+        Self::from_timestamped_edges(
+            edges
+                .into_iter()
+                .map(|(from, to, level)| (from, to, level, None)),
+        )
+    }
+
+    // from_timestamped_edges builds the graph from (truster, trustee, level,
+    // timestamp) tuples, with timestamps in unix seconds.
+    pub fn from_timestamped_edges<I, S>(edges: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (S, S, f64, Option<f64>)>,
+        S: AsRef<str>,
+    {
+        let mut peers = PeersMap::new();
+        let mut entries = vec![];
+        for (count, (from, to, level, timestamp)) in edges.into_iter().enumerate() {
+            if !level.is_finite() {
+                return Err(format!("Invalid trust level {} in edge #{}", level, count + 1));
+            }
+            if let Some(timestamp) = timestamp.filter(|t| !t.is_finite()) {
+                return Err(format!("Invalid timestamp {} in edge #{}", timestamp, count + 1));
+            }
+            let from = peers.insert_or_get(from.as_ref().to_string());
+            let to = peers.insert_or_get(to.as_ref().to_string());
+            entries.push((from, to, level, timestamp));
+        }
+
+        let (local_trust, timestamps) = timestamped_local_trust(peers.get_max_value(), entries);
+        Ok(Self::with_timestamped_graph(local_trust, timestamps, peers))
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.peers.get_max_value()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.local_trust.cs_matrix.nnz()
+    }
+
+    pub fn stats(&self) -> GraphStats {
+        graph_stats(&self.local_trust)
+    }
+
+    // set_edge adds or replaces the trust level from one peer to another,
+    // adding unknown peers. A zero level removes the edge.
+    pub fn set_edge(&mut self, from: &str, to: &str, level: f64) -> Result<(), String> {
+        self.set_edge_at(from, to, level, None)
+    }
+
+    // set_edge_at is set_edge with the timestamp of the edge, in unix seconds.
+    // Edges without one are not decayed.
+    pub fn set_edge_at(
+        &mut self,
+        from: &str,
+        to: &str,
+        level: f64,
+        timestamp: Option<f64>,
+    ) -> Result<(), String> {
+        if !level.is_finite() {
+            return Err(format!("Invalid trust level: {}", level));
+        }
+        if let Some(timestamp) = timestamp.filter(|t| !t.is_finite()) {
+            return Err(format!("Invalid timestamp: {}", timestamp));
+        }
+
+        let from = self.peers.insert_or_get(from.to_string());
+        let to = self.peers.insert_or_get(to.to_string());
+        let dim = self.peers.get_max_value();
+        if dim > self.local_trust.dims().0 {
+            self.local_trust.set_dim(dim, dim);
+            self.timestamps.resize(dim, vec![]);
+        }
+
+        let edges = self.edge_range(from, to);
+        let position = edges.start;
+        let row = &mut self.local_trust.cs_matrix.entries[from];
+        let row_timestamps = &mut self.timestamps[from];
+        row.drain(edges.clone());
+        row_timestamps.drain(edges);
+        if level != 0.0 {
+            row.insert(position, Entry { index: to, value: level });
+            row_timestamps.insert(position, timestamp);
+        }
+        self.scores = None;
+        self.last = None;
+        Ok(())
+    }
+
+    // remove_edge returns whether the edge existed.
+    pub fn remove_edge(&mut self, from: &str, to: &str) -> bool {
+        let (from, to) = match (self.peers.map.get(from), self.peers.map.get(to)) {
+            (Some(&from), Some(&to)) => (from, to),
+            _ => return false,
+        };
+
+        let edges = self.edge_range(from, to);
+        if edges.is_empty() {
+            return false;
+        }
+        self.local_trust.cs_matrix.entries[from].drain(edges.clone());
+        self.timestamps[from].drain(edges);
+        self.scores = None;
+        self.last = None;
+        true
+    }
+
+    // edge_range is the position of the entries from one peer to another in
+    // its row, which is sorted by index; there can be several from CSV.
+    fn edge_range(&self, from: usize, to: usize) -> Range<usize> {
+        let row = &self.local_trust.cs_matrix.entries[from];
+        row.partition_point(|entry| entry.index < to)..row.partition_point(|entry| entry.index <= to)
+    }
+
+    // local_trust is the graph weighed by the given decay, if any.
+    fn local_trust(
+        &self,
+        decay: Option<&Decay>,
+        reference: Option<f64>,
+    ) -> Result<CSRMatrix, String> {
+        match decay {
+            Some(decay) => decay_local_trust(&self.local_trust, &self.timestamps, decay, reference),
+            None => Ok(self.local_trust.clone()),
+        }
+    }
+
+    pub fn compute(
+        &mut self,
+        pretrust_csv: &str,
+        options: &ComputeOptions,
+    ) -> Result<Vec<(String, f64)>, String> {
+        self.compute_with_observer(pretrust_csv, options, None)
+    }
+
+    pub fn compute_with_observer(
+        &mut self,
+        pretrust_csv: &str,
+        options: &ComputeOptions,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vec<(String, f64)>, String> {
+        let local_trust = self.local_trust(options.decay.as_ref(), options.decay_reference)?;
+        let result = calculate(
+            local_trust,
+            &self.peers,
+            pretrust_csv,
+            options,
+            observer,
+        );
+        self.finish(result)
+    }
+
+    // compute_with_pretrust takes pretrust levels by peer name instead of CSV.
+    // Every pretrusted peer must be in the graph.
+    pub fn compute_with_pretrust<I, S>(
+        &mut self,
+        pretrust: I,
+        options: &ComputeOptions,
+        observer: Option<&dyn ComputeObserver>,
+    ) -> Result<Vec<(String, f64)>, String>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let timer = Timer::start();
+        let mut entries = vec![];
+        for (peer, level) in pretrust {
+            let index = self
+                .peers
+                .map
+                .get(peer.as_ref())
+                .ok_or_else(|| format!("Invalid peer: {}", peer.as_ref()))?;
+            if !level.is_finite() {
+                return Err(format!("Invalid trust level: {}", level));
+            }
+            entries.push(Entry {
+                index: *index,
+                value: level,
+            });
+        }
+        let pre_trust = Vector::new(self.peer_count(), entries);
+        let local_trust = self.local_trust(options.decay.as_ref(), options.decay_reference)?;
+        let parse_ms = timer.elapsed_ms();
+
+        let result = calculate_named(
+            local_trust,
+            &self.peers,
+            pre_trust,
+            options,
+            observer,
+        )
+        .map(|(scores, mut result)| {
+            result.timings.parse_ms += parse_ms;
+            result.timings.total_ms += parse_ms;
+            (scores, result)
+        });
+        self.finish(result)
+    }
+
+    fn finish(
+        &mut self,
+        result: Result<(Vec<(String, f64)>, MatrixCompute), String>,
+    ) -> Result<Vec<(String, f64)>, String> {
+        let (scores, result) = result?;
+        self.scores = Some(scores.iter().cloned().collect());
+        self.timings = Some(result.timings.clone());
+        self.last = Some(result);
+        Ok(scores)
+    }
+
+    // timings returns the phase timings of the last compute; parsing only
+    // covers the pretrust, as local trust is parsed once on load.
+    pub fn timings(&self) -> Option<&PhaseTimings> {
+        self.timings.as_ref()
+    }
+
+    // explain breaks down a peer's score from the last compute into its
+    // pretrust share and the contributions of the peers that trust it.
+    pub fn explain(&self, peer: &str) -> Result<Explanation, String> {
+        let last = self
+            .last
+            .as_ref()
+            .ok_or("No scores to explain, run compute first")?;
+        let index = *self
+            .peers
+            .map
+            .get(peer)
+            .ok_or_else(|| format!("Invalid peer: {}", peer))?;
+        let teleport = last
+            .teleport
+            .as_ref()
+            .ok_or("Scores of this algorithm cannot be explained")?;
+
+        Ok(explain_scores(
+            index,
+            &last.scores,
+            &last.trust,
+            &last.local_trust_t,
+            teleport,
+            last.alpha,
+            &self.peers,
+        ))
+    }
+
+    // trust_paths returns up to k of the highest weight paths of at most
+    // max_depth edges from the pretrusted peers of the last compute to the peer.
+    pub fn trust_paths(
+        &self,
+        peer: &str,
+        k: usize,
+        max_depth: usize,
+    ) -> Result<Vec<NamedTrustPath>, String> {
+        let last = self
+            .last
+            .as_ref()
+            .ok_or("No pretrust to start paths from, run compute first")?;
+        let index = *self
+            .peers
+            .map
+            .get(peer)
+            .ok_or_else(|| format!("Invalid peer: {}", peer))?;
+
+        let local_trust = self.local_trust(last.decay.as_ref(), last.decay_reference)?;
+        let paths = top_trust_paths(&local_trust, &last.pre_trust, index, k, max_depth);
+        Ok(name_paths(paths, &self.peers))
+    }
+
+    // reachability reports which peers the seeds of the last compute reach:
+    // its teleport distribution, or the pretrust for algorithms without one.
+    pub fn reachability(&self) -> Result<Reachability, String> {
+        let last = self
+            .last
+            .as_ref()
+            .ok_or("No pretrust to start from, run compute first")?;
+        let seeds = last.teleport.as_ref().unwrap_or(&last.pre_trust);
+        let local_trust = self.local_trust(last.decay.as_ref(), last.decay_reference)?;
+        Ok(reachability(&local_trust, seeds, &self.peers))
+    }
+
+    // communities divides the graph into communities with louvain.
+    pub fn communities(&self) -> Communities {
+        louvain(&self.local_trust)
+    }
+
+    // community_scores normalizes the scores of the last compute within each
+    // community, so that members' scores sum to one.
+    pub fn community_scores(&self, communities: &Communities) -> Result<Vec<CommunityScores>, String> {
+        let last = self.last.as_ref().ok_or("No scores, run compute first")?;
+        let global = self.dense_scores(&last.scores);
+
+        Ok(communities
+            .members()
+            .into_iter()
+            .enumerate()
+            .map(|(community, members)| {
+                let trust: f64 = members.iter().map(|&i| global[i]).sum();
+                let scores = members
+                    .iter()
+                    .filter(|&&i| global[i] != 0.0)
+                    .map(|&i| (self.peers.map_reversed[&i].clone(), global[i] / trust))
+                    .collect();
+                self.community_result(community, &members, trust, scores)
+            })
+            .collect())
+    }
+
+    // compute_within_communities computes scores on each community alone,
+    // with the pretrust of the last compute restricted to its members, or a
+    // uniform pretrust if none of them is pretrusted.
+    pub fn compute_within_communities(
+        &self,
+        communities: &Communities,
+        options: &ComputeOptions,
+    ) -> Result<Vec<CommunityScores>, String> {
+        let last = self.last.as_ref().ok_or("No pretrust, run compute first")?;
+        let global = self.dense_scores(&last.scores);
+        let graph = self.local_trust(options.decay.as_ref(), options.decay_reference)?;
+        let options = ComputeOptions {
+            decay: None,
+            ..options.clone()
+        };
+
+        let mut results = vec![];
+        for (community, members) in communities.members().into_iter().enumerate() {
+            let local_trust = select_peers(&graph, &members);
+            let mut pre_trust = select_entries(&last.pre_trust, &members);
+            if pre_trust.entries.is_empty() {
+                pre_trust = Vector::new(
+                    members.len(),
+                    (0..members.len()).map(|index| Entry { index, value: 1.0 }).collect(),
+                );
+            }
+            let (scores, _) = calculate_from_matrix(local_trust, pre_trust, &options, None)?;
+
+            let scores = scores
+                .entries
+                .iter()
+                .map(|e| (self.peers.map_reversed[&members[e.index]].clone(), e.value))
+                .collect();
+            let trust = members.iter().map(|&i| global[i]).sum();
+            results.push(self.community_result(community, &members, trust, scores));
+        }
+        Ok(results)
+    }
+
+    fn dense_scores(&self, scores: &Vector) -> Vec<f64> {
+        let mut dense = vec![0.0; self.peer_count().max(scores.dim)];
+        for e in &scores.entries {
+            dense[e.index] = e.value;
+        }
+        dense
+    }
+
+    fn community_result(
+        &self,
+        community: usize,
+        members: &[usize],
+        trust: f64,
+        mut scores: Vec<(String, f64)>,
+    ) -> CommunityScores {
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        CommunityScores {
+            community,
+            size: members.len(),
+            trust,
+            scores,
+        }
+    }
+
+    // detect_collusion flags suspicious groups in the graph, using the scores
+    // of the last compute, if any, to find groups holding too much trust.
+    pub fn detect_collusion(&self, options: &CollusionOptions) -> Vec<SuspiciousGroup> {
+        let scores = self.last.as_ref().map(|last| &last.scores);
+        detect_collusion(&self.local_trust, scores, &self.peers, options)
+    }
+
+    // score returns a peer's score from the last compute, zero for known peers
+    // without one. It is None for unknown peers and after the graph was edited.
+    pub fn score(&self, peer: &str) -> Option<f64> {
+        let scores = self.scores.as_ref()?;
+        if !self.peers.map.contains_key(peer) {
+            return None;
+        }
+        Some(scores.get(peer).cloned().unwrap_or(0.0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_calculate_from_csv() {
@@ -115,4 +875,280 @@ mod tests {
         assert_eq!(entries[0].1, 0.4034661335389856);
         assert_eq!(entries[1].0, "0x9fc3b33884e1d056a8ca979833d686abd267f9f8");
     }
+
+    #[test]
+    fn test_calculate_from_csv_with_options() {
+        let localtrust_csv = "alice,bob,1\nbob,carol,1\ncarol,alice,1\nalice,carol,-1";
+        let pretrust_csv = "alice,1";
+
+        let ignored = calculate_from_csv_with_options(
+            localtrust_csv,
+            pretrust_csv,
+            &ComputeOptions::default(),
+        )
+        .unwrap();
+        let discounted = calculate_from_csv_with_options(
+            localtrust_csv,
+            pretrust_csv,
+            &ComputeOptions {
+                distrust: DistrustPolicy::Discount,
+                ..ComputeOptions::default()
+            },
+        )
+        .unwrap();
+
+        let score = |entries: &Vec<(String, f64)>, peer: &str| {
+            entries.iter().find(|(name, _)| name == peer).unwrap().1
+        };
+        assert_eq!(score(&ignored, "bob"), score(&discounted, "bob"));
+        assert!(score(&discounted, "carol") < score(&ignored, "carol"));
+
+        let result = calculate_from_csv_with_options(
+            localtrust_csv,
+            pretrust_csv,
+            &ComputeOptions {
+                max_iterations: Some(2),
+                ..ComputeOptions::default()
+            },
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_trust_engine() {
+        let localtrust_csv = "i,j,v\nalice,bob,1\nbob,carol,1\ncarol,alice,1";
+        let mut engine = TrustEngine::from_csv(localtrust_csv).unwrap();
+        assert_eq!((engine.peer_count(), engine.edge_count()), (3, 3));
+        assert_eq!(engine.score("alice"), None);
+
+        let options = ComputeOptions::default();
+        let scores = engine.compute("alice,1", &options).unwrap();
+        assert_eq!(
+            scores,
+            calculate_from_csv_with_options(localtrust_csv, "alice,1", &options).unwrap()
+        );
+        assert_eq!(engine.score("bob"), Some(scores.iter().find(|(p, _)| p == "bob").unwrap().1));
+        assert_eq!(engine.score("dave"), None);
+
+        engine.set_edge("alice", "dave", 2.0).unwrap();
+        engine.set_edge("alice", "bob", 0.0).unwrap();
+        assert!(!engine.remove_edge("bob", "alice"));
+        assert!(engine.remove_edge("carol", "alice"));
+        engine.set_edge("carol", "alice", 1.0).unwrap();
+        assert_eq!((engine.peer_count(), engine.edge_count()), (4, 3));
+        assert_eq!(engine.score("bob"), None);
+
+        let edited = "alice,dave,2\nbob,carol,1\ncarol,alice,1";
+        let scores = engine.compute("alice,1\nbob,1", &options).unwrap();
+        let mut expected = calculate_from_csv_with_options(edited, "alice,1\nbob,1", &options).unwrap();
+        // bob has no incoming trust any more, but is still a known peer.
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut sorted = scores.clone();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(sorted.len(), expected.len());
+        for ((p1, s1), (p2, s2)) in sorted.iter().zip(&expected) {
+            assert_eq!(p1, p2);
+            assert!((s1 - s2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_calculate_from_csv_with_timings() {
+        let (entries, timings) = calculate_from_csv_with_timings(
+            "alice,bob,1\nbob,alice,1",
+            "alice,1",
+            &ComputeOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let phases = [
+            timings.parse_ms,
+            timings.canonicalize_ms,
+            timings.transpose_ms,
+            timings.iterate_ms,
+            timings.discount_ms,
+            timings.sort_ms,
+        ];
+        assert!(phases.iter().all(|&ms| ms >= 0.0));
+        assert!(timings.iterate_ms > 0.0);
+        assert!(phases.iter().sum::<f64>() <= timings.total_ms);
+    }
+
+    #[test]
+    fn test_trust_engine_from_edges() {
+        let edges = vec![("alice", "bob", 1.0), ("bob", "carol", 1.0), ("carol", "alice", 1.0)];
+        let mut engine = TrustEngine::from_edges(edges).unwrap();
+        let options = ComputeOptions::default();
+
+        let scores = engine
+            .compute_with_pretrust(vec![("alice", 1.0)], &options, None)
+            .unwrap();
+        assert_eq!(
+            scores,
+            calculate_from_csv_with_options("alice,bob,1\nbob,carol,1\ncarol,alice,1", "alice,1", &options)
+                .unwrap()
+        );
+        assert!(engine.timings().is_some());
+        assert_eq!(engine.stats().largest_component, 3);
+        let paths = engine.trust_paths("carol", 5, 3).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].peers, vec!["alice", "bob", "carol"]);
+        assert_eq!(paths[0].weight, 1.0);
+        assert!(engine
+            .compute_with_pretrust(vec![("dave", 1.0)], &options, None)
+            .is_err());
+        assert!(TrustEngine::from_edges(vec![("a", "b", f64::NAN)]).is_err());
+
+        let mut engine = TrustEngine::new();
+        assert!(engine.compute("", &options).is_err());
+        engine.set_edge("alice", "bob", 1.0).unwrap();
+        engine.set_edge("bob", "alice", 1.0).unwrap();
+        assert_eq!(engine.compute("alice,1", &options).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_named_scores_with_nan() {
+        let mut peers = PeersMap::new();
+        for name in ["alice", "bob", "carol"] {
+            peers.insert_or_get(name.to_string());
+        }
+        let scores = Vector::new(
+            3,
+            vec![
+                Entry { index: 0, value: 0.2 },
+                Entry { index: 1, value: f64::NAN },
+                Entry { index: 2, value: 0.8 },
+            ],
+        );
+        let named = named_scores(&scores, &peers, false);
+        let order: Vec<&str> = named.iter().map(|(peer, _)| peer.as_str()).collect();
+        assert_eq!(order, vec!["bob", "carol", "alice"]);
+    }
+
+    #[test]
+    fn test_decayed_compute() {
+        // alice trusted bob a year before carol; dave has no timestamp.
+        let localtrust_csv = "from,to,level,timestamp\nalice,bob,1,0\nalice,carol,1,31536000\nalice,dave,1,";
+        let options = ComputeOptions {
+            decay: Some("exponential:30d".parse().unwrap()),
+            ..ComputeOptions::default()
+        };
+        let score = |scores: &[(String, f64)], peer: &str| {
+            scores.iter().find(|(p, _)| p == peer).map(|(_, s)| *s).unwrap_or(0.0)
+        };
+
+        let fresh = calculate_from_csv(localtrust_csv, "alice,1", None).unwrap();
+        assert_eq!(score(&fresh, "bob"), score(&fresh, "carol"));
+
+        let decayed = calculate_from_csv_with_options(localtrust_csv, "alice,1", &options).unwrap();
+        assert!(score(&decayed, "bob") < 0.01 * score(&decayed, "carol"));
+        assert_eq!(score(&decayed, "carol"), score(&decayed, "dave"));
+
+        // The engine keeps the timestamps and decays on each compute.
+        let mut engine = TrustEngine::from_csv(localtrust_csv).unwrap();
+        assert_eq!(engine.compute("alice,1", &options).unwrap(), decayed);
+        assert_eq!(engine.compute("alice,1", &ComputeOptions::default()).unwrap(), fresh);
+        let pretrust = [("alice", 1.0)];
+        assert_eq!(engine.compute_with_pretrust(pretrust, &options, None).unwrap(), decayed);
+
+        // An edge set without a timestamp is not decayed.
+        engine.set_edge("alice", "bob", 1.0).unwrap();
+        let scores = engine.compute("alice,1", &options).unwrap();
+        assert_eq!(score(&scores, "bob"), score(&scores, "carol"));
+        engine.set_edge_at("alice", "bob", 1.0, Some(0.0)).unwrap();
+        assert_eq!(engine.compute("alice,1", &options).unwrap(), decayed);
+
+        let edges = [("alice", "bob", 1.0, Some(0.0)), ("alice", "carol", 1.0, Some(31536000.0))];
+        let mut engine = TrustEngine::from_timestamped_edges(edges).unwrap();
+        let scores = engine.compute("alice,1", &options).unwrap();
+        assert!(score(&scores, "bob") < 0.01 * score(&scores, "carol"));
+
+        // Decay cannot be applied without timestamps, so it is an error.
+        let mut engine = TrustEngine::from_edges([("alice", "bob", 1.0)]).unwrap();
+        assert!(engine.compute("alice,1", &options).is_err());
+        let local_trust = CSRMatrix::new(2, 2, vec![(0, 1, 1.0)]);
+        let pre_trust = Vector::new(2, vec![Entry { index: 0, value: 1.0 }]);
+        assert!(calculate_from_matrix(local_trust, pre_trust, &options, None).is_err());
+    }
+
+    #[test]
+    fn test_community_scores() {
+        // alice, bob and carol trust each other, as do dave, erin and frank;
+        // carol also trusts dave.
+        let mut edges = vec![("carol", "dave", 0.5)];
+        for group in [["alice", "bob", "carol"], ["dave", "erin", "frank"]] {
+            for from in group {
+                for to in group.iter().filter(|&&to| to != from) {
+                    edges.push((from, to, 1.0));
+                }
+            }
+        }
+        let mut engine = TrustEngine::from_edges(edges).unwrap();
+        let communities = engine.communities();
+        assert_eq!(communities.count, 2);
+        assert!(engine.community_scores(&communities).is_err());
+
+        let options = ComputeOptions::default();
+        engine.compute("alice,1", &options).unwrap();
+        let normalized = engine.community_scores(&communities).unwrap();
+        for community in &normalized {
+            let sum: f64 = community.scores.iter().map(|(_, score)| score).sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(normalized[0].scores[0].0, "alice");
+        assert!(normalized[0].trust > normalized[1].trust);
+
+        // Nobody in the second community is pretrusted, so within it
+        // everyone starts equal and the scores are symmetric.
+        let within = engine.compute_within_communities(&communities, &options).unwrap();
+        assert_eq!(within[1].size, 3);
+        for (_, score) in &within[1].scores {
+            assert!((score - 1.0 / 3.0).abs() < 1e-6);
+        }
+        assert_eq!(within[0].scores[0].0, "alice");
+    }
+
+    #[test]
+    fn test_unreachable_peers() {
+        let localtrust_csv = "alice,bob,1\nbob,carol,2\nbob,alice,1\ncarol,dave,-1\nerin,frank,1\nfrank,dave,1";
+        let options = ComputeOptions {
+            epsilon: Some(1e-12),
+            distrust: DistrustPolicy::Discount,
+            ..ComputeOptions::default()
+        };
+        // dave is unreachable but still discounted by carol's distrust.
+        let scores = calculate_from_csv_with_options(localtrust_csv, "alice,1", &options).unwrap();
+        assert_eq!(scores.len(), 4);
+        assert!(scores[3].1 < 0.0);
+
+        let pruned = ComputeOptions {
+            prune_unreachable: true,
+            ..options.clone()
+        };
+        let pruned = calculate_from_csv_with_options(localtrust_csv, "alice,1", &pruned).unwrap();
+        assert_eq!(pruned.len(), scores.len());
+        for (a, b) in scores.iter().zip(&pruned) {
+            assert_eq!(a.0, b.0);
+            assert!((a.1 - b.1).abs() < 1e-9);
+        }
+
+        let included = ComputeOptions {
+            prune_unreachable: true,
+            include_unreachable: true,
+            ..options
+        };
+        let mut engine = TrustEngine::from_csv(localtrust_csv).unwrap();
+        let all = engine.compute("alice,1", &included).unwrap();
+        assert_eq!(all.len(), 6);
+        let tail: Vec<(&str, f64)> = all[4..].iter().map(|(peer, score)| (peer.as_str(), *score)).collect();
+        assert_eq!(tail, vec![("erin", 0.0), ("frank", 0.0)]);
+        assert!(engine.explain("carol").is_ok());
+
+        let report = engine.reachability().unwrap();
+        assert_eq!(report.reachable, 3);
+        assert_eq!(report.unreachable, vec!["dave", "erin", "frank"]);
+        assert!(report.disconnected_seeds.is_empty());
+    }
 }
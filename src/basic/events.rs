@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+// Event is one raw interaction, such as a follow or a transfer, from which
+// local trust is derived. value scales the event's weight, e.g. the amount of
+// a transfer or the weight of an attestation, and is 1 if not given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+    pub value: f64,
+}
+
+// Aggregation combines the weighted events between two peers into a trust
+// level. Positive and negative weights are aggregated separately and the
+// negative part subtracted, so that a few reports are not drowned out by many
+// likes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    Sum,
+    // ln(1 + sum), so that each further interaction adds less.
+    LogSum,
+    // The sum, capped at the given level.
+    Cap(f64),
+}
+
+impl Aggregation {
+    fn apply(&self, sum: f64) -> f64 {
+        match *self {
+            Aggregation::Sum => sum,
+            Aggregation::LogSum => sum.ln_1p(),
+            Aggregation::Cap(max) => sum.min(max),
+        }
+    }
+}
+
+// On the command line Aggregation is written sum, log_sum or cap:<level>; in
+// policy files it is "sum", "log_sum" or { cap = <level> }, as serde derives.
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sum" => Ok(Aggregation::Sum),
+            "log_sum" | "log" => Ok(Aggregation::LogSum),
+            other => match other.strip_prefix("cap:").map(|max| max.parse::<f64>()) {
+                Some(Ok(max)) if max > 0.0 => Ok(Aggregation::Cap(max)),
+                Some(_) => Err(format!("Invalid cap: {}", s)),
+                None => Err(format!("Unknown aggregation: {}", s)),
+            },
+        }
+    }
+}
+
+// EventPolicy is the weighting scheme that turns events into local trust.
+// Each event weighs the weight of its type times its value; negative weights
+// give distrust.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventPolicy {
+    pub weights: BTreeMap<String, f64>,
+    pub aggregation: Aggregation,
+}
+
+impl Default for EventPolicy {
+    fn default() -> Self {
+        let weights = [
+            ("follow", 1.0),
+            ("like", 0.2),
+            ("reply", 0.5),
+            ("transfer", 1.0),
+            ("attestation", 1.0),
+            ("report", -1.0),
+        ];
+        EventPolicy {
+            weights: weights
+                .into_iter()
+                .map(|(kind, weight)| (kind.to_string(), weight))
+                .collect(),
+            aggregation: Aggregation::Sum,
+        }
+    }
+}
+
+impl EventPolicy {
+    // Event types are matched ignoring case, so types that differ only in
+    // case are an error.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((kind, weight)) = self.weights.iter().find(|(_, w)| !w.is_finite()) {
+            return Err(format!("Invalid weight for {}: {}", kind, weight));
+        }
+        let mut kinds = HashSet::new();
+        for kind in self.weights.keys() {
+            if !kinds.insert(kind.to_ascii_lowercase()) {
+                return Err(format!("Duplicate weight for {}", kind));
+            }
+        }
+        match self.aggregation {
+            Aggregation::Cap(max) if max.is_nan() || max <= 0.0 => {
+                Err(format!("Invalid cap: {}", max))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // lowercase_weights lowercases the event types, as parse_events_csv does
+    // the kinds of events.
+    pub fn lowercase_weights(&mut self) -> Result<(), String> {
+        self.validate()?;
+        self.weights = std::mem::take(&mut self.weights)
+            .into_iter()
+            .map(|(kind, weight)| (kind.to_ascii_lowercase(), weight))
+            .collect();
+        Ok(())
+    }
+}
+
+// parse_events_csv reads from,to,type[,value] rows. A first line whose third
+// field is "type" or "kind" is a header.
+pub fn parse_events_csv(csv: &str) -> Result<Vec<Event>, String> {
+    let mut events = vec![];
+    for (count, line) in csv.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if count == 0 {
+            if let Some(kind) = fields.get(2) {
+                if kind.eq_ignore_ascii_case("type") || kind.eq_ignore_ascii_case("kind") {
+                    continue;
+                }
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let error = |e: &str| {
+            format!(
+                "Cannot parse event record #{}: {:?} {:?}",
+                count + 1,
+                e,
+                line
+            )
+        };
+        if fields.len() < 3 {
+            return Err(error("Too few fields"));
+        }
+        let value = match fields.get(3) {
+            Some(field) if !field.is_empty() => field
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| error("Invalid value"))?,
+            _ => 1.0,
+        };
+        events.push(Event {
+            from: fields[0].to_string(),
+            to: fields[1].to_string(),
+            kind: fields[2].to_ascii_lowercase(),
+            value,
+        });
+    }
+    Ok(events)
+}
+
+// derive_local_trust aggregates events into (truster, trustee, level)
+// triples, in the order each pair first appears. Types are matched ignoring
+// case. Events of a type without a weight are an error, self-interactions are
+// skipped and pairs whose level comes to zero are left out.
+pub fn derive_local_trust(
+    events: &[Event],
+    policy: &EventPolicy,
+) -> Result<Vec<(String, String, f64)>, String> {
+    policy.validate()?;
+    let weights: HashMap<String, f64> = policy
+        .weights
+        .iter()
+        .map(|(kind, weight)| (kind.to_ascii_lowercase(), *weight))
+        .collect();
+    // (positive, negative) weight sums by pair
+    let mut sums: Vec<(&str, &str, f64, f64)> = vec![];
+    let mut pairs: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for event in events {
+        let weight = weights
+            .get(&event.kind)
+            .ok_or_else(|| format!("Unknown event type: {}", event.kind))?
+            * event.value;
+        if event.from == event.to {
+            continue;
+        }
+        let next = sums.len();
+        let i = *pairs.entry((&event.from, &event.to)).or_insert(next);
+        if i == next {
+            sums.push((&event.from, &event.to, 0.0, 0.0));
+        }
+        if weight > 0.0 {
+            sums[i].2 += weight;
+        } else {
+            sums[i].3 -= weight;
+        }
+    }
+
+    Ok(sums
+        .into_iter()
+        .map(|(from, to, positive, negative)| {
+            let level = policy.aggregation.apply(positive) - policy.aggregation.apply(negative);
+            (from.to_string(), to.to_string(), level)
+        })
+        .filter(|(_, _, level)| *level != 0.0)
+        .collect())
+}
+
+// local_trust_to_csv writes triples as local trust CSV, without a header.
+pub fn local_trust_to_csv(local_trust: &[(String, String, f64)]) -> String {
+    let mut csv = String::new();
+    for (from, to, level) in local_trust {
+        let _ = writeln!(csv, "{},{},{}", from, to, level);
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::localtrust::read_local_trust_from_csv;
+
+    const EVENTS: &str = "from,to,type,value\n\
+                          alice,bob,follow\n\
+                          alice,bob,like\n\
+                          alice,bob,like\n\
+                          bob,carol,transfer,100\n\
+                          carol,alice,report\n\
+                          carol,alice,Like,\n\
+                          dave,alice,attestation,0";
+
+    #[test]
+    fn test_parse_events_csv() {
+        let events = parse_events_csv(EVENTS).unwrap();
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[5].kind, "like");
+        assert_eq!(events[5].value, 1.0);
+        assert_eq!(events[3].value, 100.0);
+
+        assert!(parse_events_csv("alice,bob").is_err());
+        assert!(parse_events_csv("alice,bob,transfer,lots").is_err());
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let events = parse_events_csv(EVENTS).unwrap();
+        let local_trust = derive_local_trust(&events, &EventPolicy::default()).unwrap();
+        let levels: Vec<f64> = local_trust.iter().map(|(_, _, level)| *level).collect();
+        assert_eq!(local_trust[0].0, "alice");
+        assert!((levels[0] - 1.4).abs() < 1e-12);
+        assert_eq!(levels[1], 100.0);
+        // A report and a like: positive and negative weights are aggregated apart.
+        assert!((levels[2] + 0.8).abs() < 1e-12);
+        // dave's attestation of value 0 comes to zero and is left out.
+        assert_eq!(local_trust.len(), 3);
+
+        let damped = EventPolicy {
+            aggregation: Aggregation::LogSum,
+            ..EventPolicy::default()
+        };
+        assert_eq!(derive_local_trust(&events, &damped).unwrap()[1].2, 101f64.ln());
+
+        let csv = local_trust_to_csv(&local_trust);
+        let (matrix, peers) = read_local_trust_from_csv(&csv).unwrap();
+        assert_eq!(peers.get_max_value(), 3);
+        assert_eq!(matrix.cs_matrix.nnz(), 3);
+    }
+
+    #[test]
+    fn test_cap() {
+        let events = parse_events_csv(EVENTS).unwrap();
+        let capped = EventPolicy {
+            aggregation: "cap:10".parse().unwrap(),
+            ..EventPolicy::default()
+        };
+        assert_eq!(derive_local_trust(&events, &capped).unwrap()[1].2, 10.0);
+
+        assert!("cap:0".parse::<Aggregation>().is_err());
+        let invalid = EventPolicy {
+            aggregation: Aggregation::Cap(0.0),
+            ..EventPolicy::default()
+        };
+        assert!(derive_local_trust(&events, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_unknown_kind() {
+        let unknown = parse_events_csv("alice,bob,recast").unwrap();
+        assert!(derive_local_trust(&unknown, &EventPolicy::default()).is_err());
+
+        let mut policy = EventPolicy::default();
+        policy.weights.insert("recast".to_string(), 0.5);
+        assert_eq!(derive_local_trust(&unknown, &policy).unwrap()[0].2, 0.5);
+    }
+
+    #[test]
+    fn test_weight_case() {
+        let events = parse_events_csv("alice,bob,Recast").unwrap();
+        let mut policy = EventPolicy::default();
+        policy.weights.insert("ReCast".to_string(), 0.5);
+        assert_eq!(derive_local_trust(&events, &policy).unwrap()[0].2, 0.5);
+
+        policy.lowercase_weights().unwrap();
+        assert_eq!(policy.weights.get("recast"), Some(&0.5));
+
+        policy.weights.insert("Follow".to_string(), 2.0);
+        assert!(policy.validate().is_err());
+        assert!(policy.lowercase_weights().is_err());
+    }
+
+    #[test]
+    fn test_self_loop() {
+        let events = parse_events_csv("carol,carol,follow\nalice,alice,report").unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(derive_local_trust(&events, &EventPolicy::default())
+            .unwrap()
+            .is_empty());
+    }
+}
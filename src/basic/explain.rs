@@ -0,0 +1,138 @@
+use crate::basic::util::PeersMap;
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+
+use serde::Serialize;
+
+// Contribution is the trust one peer passes to another in the final iteration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contribution {
+    pub peer: String,
+    // The truster's trust, before any distrust discount.
+    pub trust: f64,
+    // The truster's canonicalized local trust in the explained peer.
+    pub weight: f64,
+    // (1 - alpha) * trust * weight
+    pub contribution: f64,
+}
+
+// Explanation breaks a converged score down as
+// score = pretrust + sum(contributions) - discount, up to the convergence threshold.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub peer: String,
+    pub score: f64,
+    // alpha * p[i]
+    pub pretrust: f64,
+    // Sorted by descending contribution.
+    pub contributions: Vec<Contribution>,
+    // Subtracted by the distrust discount policy, otherwise zero.
+    pub discount: f64,
+}
+
+fn value_at(v: &Vector, index: usize) -> f64 {
+    match v.entries.binary_search_by_key(&index, |e| e.index) {
+        Ok(position) => v.entries[position].value,
+        Err(_) => 0.0,
+    }
+}
+
+// explain_scores explains the score of peer i from the final scores, the
+// trust they were discounted from, and the canonicalized transposed local
+// trust and teleport vector the trust was computed with.
+pub fn explain_scores(
+    i: usize,
+    scores: &Vector,
+    trust: &Vector,
+    local_trust_t: &CSRMatrix,
+    teleport: &Vector,
+    alpha: f64,
+    peers: &PeersMap,
+) -> Explanation {
+    let name = |index: usize| peers.map_reversed.get(&index).cloned().unwrap_or_default();
+
+    // Row i of the transposed matrix holds the trust i receives.
+    let mut contributions: Vec<Contribution> = local_trust_t.cs_matrix.entries[i]
+        .iter()
+        .filter_map(|e| {
+            let trust = value_at(trust, e.index);
+            let contribution = (1.0 - alpha) * trust * e.value;
+            (contribution != 0.0).then(|| Contribution {
+                peer: name(e.index),
+                trust,
+                weight: e.value,
+                contribution,
+            })
+        })
+        .collect();
+    contributions.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+
+    let score = value_at(scores, i);
+    Explanation {
+        peer: name(i),
+        score,
+        pretrust: alpha * value_at(teleport, i),
+        contributions,
+        discount: value_at(trust, i) - score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basic::algorithm::Algorithm;
+    use crate::basic::engine::{ComputeOptions, DistrustPolicy, TrustEngine};
+
+    #[test]
+    fn test_explain() {
+        let edges = vec![
+            ("alice", "bob", 2.0),
+            ("alice", "carol", 1.0),
+            ("bob", "carol", 1.0),
+            ("carol", "bob", 1.0),
+            ("dave", "bob", -1.0),
+            ("carol", "dave", 1.0),
+        ];
+        let mut engine = TrustEngine::from_edges(edges).unwrap();
+        assert!(engine.explain("bob").is_err());
+
+        for distrust in [DistrustPolicy::Ignore, DistrustPolicy::Discount] {
+            let options = ComputeOptions {
+                epsilon: Some(1e-12),
+                distrust,
+                ..ComputeOptions::default()
+            };
+            engine
+                .compute_with_pretrust(vec![("alice", 1.0)], &options, None)
+                .unwrap();
+
+            let explanation = engine.explain("bob").unwrap();
+            assert_eq!(explanation.score, engine.score("bob").unwrap());
+            assert_eq!(explanation.pretrust, 0.0);
+            let peers: Vec<&str> = explanation
+                .contributions
+                .iter()
+                .map(|c| c.peer.as_str())
+                .collect();
+            assert_eq!(peers, vec!["alice", "carol"]);
+
+            let sum: f64 = explanation.contributions.iter().map(|c| c.contribution).sum();
+            let total = explanation.pretrust + sum - explanation.discount;
+            assert!((total - explanation.score).abs() < 1e-9);
+            assert_eq!(explanation.discount > 0.0, distrust == DistrustPolicy::Discount);
+
+            let alice = engine.explain("alice").unwrap();
+            assert_eq!(alice.pretrust, 0.5);
+        }
+
+        assert!(engine.explain("erin").is_err());
+
+        let options = ComputeOptions {
+            algorithm: Algorithm::SybilRank,
+            ..ComputeOptions::default()
+        };
+        engine
+            .compute_with_pretrust(vec![("alice", 1.0)], &options, None)
+            .unwrap();
+        assert!(engine.explain("bob").is_err());
+    }
+}
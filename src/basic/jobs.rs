@@ -0,0 +1,336 @@
+use super::eigentrust::{ComputeControl, CANCELLED_ERROR};
+use super::engine::{calculate_from_csv_with_observer, ComputeOptions};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone)]
+pub struct JobRequest {
+    pub localtrust_csv: String,
+    pub pretrust_csv: String,
+    pub options: ComputeOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running { iteration: usize, delta: f64 },
+    Done { peers: usize },
+    Failed { error: String },
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running { .. })
+    }
+}
+
+enum JobState {
+    Queued(JobRequest),
+    Running,
+    Done(Vec<(String, f64)>),
+    Failed(String),
+    Cancelled,
+}
+
+struct Job {
+    state: JobState,
+    control: Arc<ComputeControl>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        match &self.state {
+            JobState::Queued(_) => JobStatus::Queued,
+            JobState::Running => JobStatus::Running {
+                iteration: self.control.iteration(),
+                delta: self.control.delta(),
+            },
+            JobState::Done(scores) => JobStatus::Done {
+                peers: scores.len(),
+            },
+            JobState::Failed(error) => JobStatus::Failed {
+                error: error.clone(),
+            },
+            JobState::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    jobs: BTreeMap<JobId, Job>,
+    queue: VecDeque<JobId>,
+    next_id: JobId,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    jobs: Mutex<Jobs>,
+    // Signalled when a job is queued or finished, or on shutdown.
+    changed: Condvar,
+}
+
+// JobQueue runs computes on a pool of worker threads. Submitting returns a
+// job ID that can be polled for status, cancelled, and used to fetch the
+// scores once done. Finished jobs are kept until removed.
+pub struct JobQueue {
+    shared: Arc<Shared>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl JobQueue {
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared::default());
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || worker(&shared))
+            })
+            .collect();
+        JobQueue { shared, workers }
+    }
+
+    pub fn submit(&self, request: JobRequest) -> JobId {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        jobs.next_id += 1;
+        let id = jobs.next_id;
+        jobs.jobs.insert(
+            id,
+            Job {
+                state: JobState::Queued(request),
+                control: Arc::new(ComputeControl::new()),
+            },
+        );
+        jobs.queue.push_back(id);
+        self.shared.changed.notify_all();
+        id
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        let jobs = self.shared.jobs.lock().unwrap();
+        jobs.jobs.get(&id).map(|job| job.status())
+    }
+
+    pub fn ids(&self) -> Vec<JobId> {
+        let jobs = self.shared.jobs.lock().unwrap();
+        jobs.jobs.keys().cloned().collect()
+    }
+
+    // result returns the scores of a finished job, sorted by score.
+    pub fn result(&self, id: JobId) -> Option<Vec<(String, f64)>> {
+        let jobs = self.shared.jobs.lock().unwrap();
+        match jobs.jobs.get(&id).map(|job| &job.state) {
+            Some(JobState::Done(scores)) => Some(scores.clone()),
+            _ => None,
+        }
+    }
+
+    // cancel stops a queued or running job. It returns false for unknown or finished jobs.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        let job = match jobs.jobs.get_mut(&id) {
+            Some(job) => job,
+            None => return false,
+        };
+        match job.state {
+            JobState::Queued(_) => {
+                job.state = JobState::Cancelled;
+                jobs.queue.retain(|&queued| queued != id);
+                self.shared.changed.notify_all();
+                true
+            }
+            JobState::Running => {
+                job.control.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // remove cancels the job if needed and forgets it.
+    pub fn remove(&self, id: JobId) -> bool {
+        self.cancel(id);
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        jobs.jobs.remove(&id).is_some()
+    }
+
+    // wait blocks until the job is finished and returns its final status.
+    pub fn wait(&self, id: JobId) -> Option<JobStatus> {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        loop {
+            let status = jobs.jobs.get(&id)?.status();
+            if status.is_finished() {
+                return Some(status);
+            }
+            jobs = self.shared.changed.wait(jobs).unwrap();
+        }
+    }
+}
+
+impl Drop for JobQueue {
+    fn drop(&mut self) {
+        {
+            let mut jobs = self.shared.jobs.lock().unwrap();
+            jobs.shutdown = true;
+            for job in jobs.jobs.values() {
+                job.control.cancel();
+            }
+        }
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(shared: &Shared) {
+    loop {
+        let (id, request, control) = {
+            let mut jobs = shared.jobs.lock().unwrap();
+            loop {
+                if jobs.shutdown {
+                    return;
+                }
+                if let Some(id) = jobs.queue.pop_front() {
+                    let job = jobs.jobs.get_mut(&id).expect("queued job exists");
+                    let state = std::mem::replace(&mut job.state, JobState::Running);
+                    if let JobState::Queued(request) = state {
+                        break (id, request, job.control.clone());
+                    }
+                    job.state = state;
+                    continue;
+                }
+                jobs = shared.changed.wait(jobs).unwrap();
+            }
+        };
+
+        log::info!("Job {} started", id);
+        let state = run_job(|| {
+            calculate_from_csv_with_observer(
+                &request.localtrust_csv,
+                &request.pretrust_csv,
+                &request.options,
+                Some(control.as_ref()),
+            )
+        });
+        log::info!("Job {} finished", id);
+
+        let mut jobs = shared.jobs.lock().unwrap();
+        // The job may have been removed while running.
+        if let Some(job) = jobs.jobs.get_mut(&id) {
+            job.state = state;
+        }
+        shared.changed.notify_all();
+    }
+}
+
+// run_job runs a compute and turns its result into the final job state. A
+// panic fails the job instead of killing the worker, which would leave the
+// job running forever.
+fn run_job<F>(compute: F) -> JobState
+where
+    F: FnOnce() -> Result<Vec<(String, f64)>, String>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(compute)) {
+        Ok(Ok(scores)) => JobState::Done(scores),
+        Ok(Err(e)) if e == CANCELLED_ERROR => JobState::Cancelled,
+        Ok(Err(e)) => JobState::Failed(e),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            JobState::Failed(format!("Compute panicked: {}", message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(pretrust_csv: &str) -> JobRequest {
+        JobRequest {
+            localtrust_csv: "alice,bob,1\nbob,carol,1\ncarol,alice,1".to_string(),
+            pretrust_csv: pretrust_csv.to_string(),
+            options: ComputeOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_job_queue() {
+        let queue = JobQueue::new(2);
+
+        let ok = queue.submit(request("alice,1"));
+        let failed = queue.submit(request("dave,1"));
+
+        assert_eq!(queue.wait(ok), Some(JobStatus::Done { peers: 3 }));
+        assert!(matches!(queue.wait(failed), Some(JobStatus::Failed { .. })));
+
+        let scores = queue.result(ok).unwrap();
+        assert_eq!(scores.len(), 3);
+        assert!(queue.result(failed).is_none());
+
+        assert!(!queue.cancel(ok));
+        assert!(queue.remove(ok));
+        assert_eq!(queue.status(ok), None);
+        assert_eq!(queue.ids(), vec![failed]);
+    }
+
+    #[test]
+    fn test_cancel_running_job() {
+        let queue = JobQueue::new(1);
+
+        // Never converges within the iteration limit, so it runs until cancelled.
+        let slow = queue.submit(JobRequest {
+            options: ComputeOptions {
+                epsilon: Some(0.0),
+                max_iterations: Some(usize::MAX),
+                ..ComputeOptions::default()
+            },
+            ..request("alice,1")
+        });
+        let queued = queue.submit(request("alice,1"));
+
+        while !matches!(queue.status(slow), Some(JobStatus::Running { .. })) {
+            thread::yield_now();
+        }
+        assert_eq!(queue.status(queued), Some(JobStatus::Queued));
+
+        assert!(queue.cancel(queued));
+        assert!(queue.cancel(slow));
+        assert_eq!(queue.wait(slow), Some(JobStatus::Cancelled));
+        assert_eq!(queue.wait(queued), Some(JobStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_panicking_job_fails() {
+        let status = |state| {
+            Job {
+                state,
+                control: Arc::new(ComputeControl::new()),
+            }
+            .status()
+        };
+
+        assert_eq!(
+            status(run_job(|| panic!("bad input"))),
+            JobStatus::Failed {
+                error: "Compute panicked: bad input".to_string()
+            }
+        );
+        assert_eq!(
+            status(run_job(|| Err(CANCELLED_ERROR.to_string()))),
+            JobStatus::Cancelled
+        );
+    }
+}
@@ -1,9 +1,8 @@
+use super::decay::Decay;
 use super::util::PeersMap;
-use crate::sparse::entry::CooEntry;
 use crate::sparse::entry::Entry;
 use crate::sparse::matrix::CSRMatrix;
 use crate::sparse::vector::Vector;
-use std::collections::HashMap;
 
 pub fn canonicalize_local_trust(
     local_trust: &mut CSRMatrix,
@@ -64,35 +63,100 @@ pub fn extract_distrust(local_trust: &mut CSRMatrix) -> Result<CSRMatrix, String
     Ok(distrust)
 }
 
-fn parse_csv_line(line: &str, peer_indices: &mut PeersMap) -> Result<(usize, usize, f64), String> {
+// LocalTrustRecord is one line of local trust CSV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTrustRecord<'a> {
+    pub truster: &'a str,
+    pub trustee: &'a str,
+    pub level: f64,
+    pub timestamp: Option<f64>,
+}
+
+// parse_local_trust_record parses truster, trustee, an optional level (1 if
+// omitted) and an optional timestamp in unix seconds. Both the reader and
+// validate_csv use it, so that they accept the same input.
+pub fn parse_local_trust_record(line: &str) -> Result<LocalTrustRecord<'_>, String> {
     let fields: Vec<&str> = line.split(',').collect();
 
     if fields.len() < 2 {
-        return Err("Too few fields".to_string());
+        return Err("too few fields".to_string());
     }
-    let from = peer_indices.insert_or_get(fields[0].to_string());
-    let to = peer_indices.insert_or_get(fields[1].to_string());
-    let level = if fields.len() >= 3 {
-        fields[2]
-            .parse::<f64>()
-            .map_err(|_| "Invalid trust level")?
-    } else {
-        1.0
+    let level = match fields.get(2) {
+        Some(field) => {
+            let level = field
+                .parse::<f64>()
+                .map_err(|_| format!("invalid trust level {:?}", field))?;
+            if !level.is_finite() {
+                return Err(format!("trust level {:?} is not finite", field));
+            }
+            level
+        }
+        None => 1.0,
+    };
+    let timestamp = match fields.get(3).map(|f| f.trim()) {
+        Some(field) if !field.is_empty() => Some(
+            field
+                .parse::<f64>()
+                .ok()
+                .filter(|t| t.is_finite())
+                .ok_or_else(|| format!("invalid timestamp {:?}", field))?,
+        ),
+        _ => None,
     };
-    Ok((from, to, level))
+    Ok(LocalTrustRecord {
+        truster: fields[0],
+        trustee: fields[1],
+        level,
+        timestamp,
+    })
 }
 
 // todo move csv logic out of this scope, cooentry
 pub fn read_local_trust_from_csv(csv_data: &str) -> Result<(CSRMatrix, PeersMap), String> {
-    let mut entries: Vec<(usize, usize, f64)> = Vec::new();
+    read_local_trust_from_csv_with_decay(csv_data, None, None)
+}
+
+// read_local_trust_from_csv_with_decay weighs each level by the decay at the
+// age of its timestamp, before the levels are summed and canonicalized; see
+// decay_local_trust.
+pub fn read_local_trust_from_csv_with_decay(
+    csv_data: &str,
+    decay: Option<&Decay>,
+    reference: Option<f64>,
+) -> Result<(CSRMatrix, PeersMap), String> {
+    let (local_trust, timestamps, peers) = read_timestamped_local_trust_from_csv(csv_data)?;
+    match decay {
+        Some(decay) => Ok((
+            decay_local_trust(&local_trust, &timestamps, decay, reference)?,
+            peers,
+        )),
+        None => Ok((local_trust, peers)),
+    }
+}
+
+// EdgeTimestamps holds the timestamp of each local trust entry in unix
+// seconds, row by row in the order of the matrix entries; None for entries
+// without one.
+pub type EdgeTimestamps = Vec<Vec<Option<f64>>>;
+
+// read_timestamped_local_trust_from_csv keeps the timestamp column next to
+// the levels, so that decay can be applied later.
+pub fn read_timestamped_local_trust_from_csv(
+    csv_data: &str,
+) -> Result<(CSRMatrix, EdgeTimestamps, PeersMap), String> {
+    let mut entries: Vec<(usize, usize, f64, Option<f64>)> = Vec::new();
     let mut max_from = 0;
     let mut max_to = 0;
     let mut peer_indices = PeersMap::new();
 
     for (count, line) in csv_data.lines().enumerate() {
-        let parsed_result = parse_csv_line(line, &mut peer_indices);
-        match parsed_result {
-            Ok((from, to, level)) => {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_local_trust_record(line) {
+            Ok(record) => {
+                let from = peer_indices.insert_or_get(record.truster.to_string());
+                let to = peer_indices.insert_or_get(record.trustee.to_string());
                 if from > max_from {
                     max_from = from;
                 }
@@ -100,7 +164,7 @@ pub fn read_local_trust_from_csv(csv_data: &str) -> Result<(CSRMatrix, PeersMap)
                     max_to = to;
                 }
 
-                entries.push((from, to, level));
+                entries.push((from, to, record.level, record.timestamp));
             }
             Err(e) => {
                 return Err(format!(
@@ -114,7 +178,63 @@ pub fn read_local_trust_from_csv(csv_data: &str) -> Result<(CSRMatrix, PeersMap)
     }
 
     let dim = max_from.max(max_to) + 1;
-    Ok((CSRMatrix::new(dim, dim, entries), peer_indices))
+    let (local_trust, timestamps) = timestamped_local_trust(dim, entries);
+    Ok((local_trust, timestamps, peer_indices))
+}
+
+// timestamped_local_trust is CSRMatrix::new keeping the timestamp of each
+// entry, as (truster, trustee, level, timestamp).
+pub fn timestamped_local_trust(
+    dim: usize,
+    entries: Vec<(usize, usize, f64, Option<f64>)>,
+) -> (CSRMatrix, EdgeTimestamps) {
+    let mut rows = vec![Vec::new(); dim];
+    for (from, to, level, timestamp) in entries {
+        if level != 0.0 {
+            rows[from].push((Entry { index: to, value: level }, timestamp));
+        }
+    }
+
+    let mut local_trust = CSRMatrix::new(dim, dim, vec![]);
+    let mut timestamps = Vec::with_capacity(dim);
+    for (i, mut row) in rows.into_iter().enumerate() {
+        row.sort_by_key(|(entry, _)| entry.index);
+        let (entries, row_timestamps) = row.into_iter().unzip();
+        local_trust.cs_matrix.entries[i] = entries;
+        timestamps.push(row_timestamps);
+    }
+    (local_trust, timestamps)
+}
+
+// decay_local_trust returns the local trust with each level weighed by the
+// decay at the age of its timestamp. Ages are taken from reference, or else
+// from the newest timestamp, so that the same graph always gives the same
+// trust. Entries without a timestamp are not decayed, and entries decayed to
+// zero are dropped. Local trust without any timestamp is an error, as decay
+// would do nothing.
+pub fn decay_local_trust(
+    local_trust: &CSRMatrix,
+    timestamps: &EdgeTimestamps,
+    decay: &Decay,
+    reference: Option<f64>,
+) -> Result<CSRMatrix, String> {
+    decay.validate()?;
+    let newest = timestamps.iter().flatten().flatten().cloned().reduce(f64::max);
+    if newest.is_none() {
+        return Err("Decay needs local trust with timestamps".to_string());
+    }
+    let reference = reference.or(newest).unwrap_or_default();
+
+    let mut entries = vec![];
+    let rows = local_trust.cs_matrix.entries.iter().zip(timestamps);
+    for (from, (row, row_timestamps)) in rows.enumerate() {
+        for (entry, timestamp) in row.iter().zip(row_timestamps) {
+            let factor = timestamp.map_or(1.0, |timestamp| decay.factor(reference - timestamp));
+            entries.push((from, entry.index, entry.value * factor));
+        }
+    }
+    let (rows, cols) = local_trust.dims();
+    Ok(CSRMatrix::new(rows, cols, entries))
 }
 
 #[cfg(test)]
@@ -161,4 +281,42 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_decayed_local_trust() {
+        let csv = "a,b,1,1000\na,c,1,900\na,c,1\nb,c,1,1000";
+        let half_life = Decay::Exponential { half_life: 100.0 };
+
+        let (trust, _) = read_local_trust_from_csv(csv).unwrap();
+        assert_eq!(trust.row_vector(0).entries.len(), 3);
+
+        // Ages are taken from the newest timestamp; a,c without one keeps its level.
+        let (trust, _) = read_local_trust_from_csv_with_decay(csv, Some(&half_life), None).unwrap();
+        let levels: Vec<f64> = trust.row_vector(0).entries.iter().map(|e| e.value).collect();
+        assert_eq!(levels[0], 1.0);
+        assert!((levels[1] - 0.5).abs() < 1e-12);
+        assert_eq!(levels[2], 1.0);
+
+        let cutoff = Decay::Cutoff { max_age: 50.0 };
+        let (trust, _) =
+            read_local_trust_from_csv_with_decay(csv, Some(&cutoff), Some(1100.0)).unwrap();
+        let levels: Vec<f64> = trust.row_vector(0).entries.iter().map(|e| e.value).collect();
+        // Trust decayed to zero is dropped.
+        assert_eq!(levels, vec![1.0]);
+
+        // Without any timestamp decay would do nothing.
+        assert!(read_local_trust_from_csv_with_decay("a,b,1", Some(&half_life), None).is_err());
+
+        // Timestamps stay with their entries as rows are sorted.
+        let (trust, timestamps, _) = read_timestamped_local_trust_from_csv(csv).unwrap();
+        assert_eq!(trust.row_vector(0).entries.len(), timestamps[0].len());
+        assert_eq!(timestamps[0], vec![Some(1000.0), Some(900.0), None]);
+        assert_eq!(timestamps[2], vec![]);
+
+        assert!(read_local_trust_from_csv("a,b,1,yesterday").is_err());
+        assert!(read_local_trust_from_csv("a,b,inf").is_err());
+        // Blank lines are skipped, as validate_csv does.
+        let (trust, peers) = read_local_trust_from_csv("a,b,1\n\n  \nb,a,1\n").unwrap();
+        assert_eq!((trust.cs_matrix.nnz(), peers.get_max_value()), (2, 2));
+    }
 }
@@ -1,5 +1,14 @@
+pub mod algorithm;
+pub mod attack;
+pub mod decay;
 pub mod eigentrust;
 pub mod engine;
+pub mod events;
+pub mod explain;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod jobs;
 pub mod localtrust;
+pub mod output;
 pub mod trustvector;
+pub mod validate;
 pub mod util;
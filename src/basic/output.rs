@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Csv,
+    Tsv,
+    Json,
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Column is an optional output column; the peer name is always written first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Rank,
+    Score,
+    Percentile,
+    Scaled,
+    LogScore,
+}
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Rank => "rank",
+            Column::Score => "score",
+            Column::Percentile => "percentile",
+            Column::Scaled => "scaled",
+            Column::LogScore => "log_score",
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rank" => Ok(Column::Rank),
+            "score" => Ok(Column::Score),
+            "percentile" => Ok(Column::Percentile),
+            "scaled" => Ok(Column::Scaled),
+            "log" | "log_score" => Ok(Column::LogScore),
+            _ => Err(format!("Unknown output column: {}", s)),
+        }
+    }
+}
+
+pub fn parse_columns(s: &str) -> Result<Vec<Column>, String> {
+    s.split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(Column::from_str)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub columns: Vec<Column>,
+    pub header: bool,
+    pub top_n: Option<usize>,
+    pub min_score: Option<f64>,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        OutputOptions {
+            format: OutputFormat::Csv,
+            columns: vec![Column::Score],
+            // Bare peer,score rows, as the CLI has always written them.
+            header: false,
+            top_n: None,
+            min_score: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoreRow {
+    pub peer: String,
+    pub rank: usize,
+    pub score: f64,
+    pub percentile: f64,
+    pub scaled: f64,
+    pub log_score: Option<f64>,
+}
+
+impl ScoreRow {
+    fn value(&self, column: Column) -> Value {
+        match column {
+            Column::Rank => Value::from(self.rank),
+            Column::Score => Value::from(self.score),
+            Column::Percentile => Value::from(self.percentile),
+            Column::Scaled => Value::from(self.scaled),
+            Column::LogScore => self.log_score.map(Value::from).unwrap_or(Value::Null),
+        }
+    }
+
+    fn text(&self, column: Column) -> String {
+        match column {
+            Column::Rank => self.rank.to_string(),
+            Column::Score => self.score.to_string(),
+            Column::Percentile => self.percentile.to_string(),
+            Column::Scaled => self.scaled.to_string(),
+            Column::LogScore => self.log_score.map(|v| v.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+// rank_scores sorts scores in descending order and derives the rank-based columns.
+// Ties share the same (lowest) rank. Percentile is the share of peers scoring
+// less than or equal to the peer, and is computed over all scores before any filtering.
+pub fn rank_scores(scores: &[(String, f64)]) -> Vec<ScoreRow> {
+    let mut sorted: Vec<&(String, f64)> = scores.iter().collect();
+    sorted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let n = sorted.len();
+    let max_score = sorted.first().map(|(_, s)| *s).unwrap_or(0.0);
+
+    let mut rows = Vec::with_capacity(n);
+    let mut rank = 0;
+    for (i, (peer, score)) in sorted.into_iter().enumerate() {
+        if i == 0 || rows.last().map(|r: &ScoreRow| r.score) != Some(*score) {
+            rank = i + 1;
+        }
+        rows.push(ScoreRow {
+            peer: peer.clone(),
+            rank,
+            score: *score,
+            percentile: 100.0 * (n - rank + 1) as f64 / n as f64,
+            scaled: if max_score > 0.0 { score / max_score } else { 0.0 },
+            log_score: if *score > 0.0 { Some(score.log10()) } else { None },
+        });
+    }
+
+    rows
+}
+
+pub fn filter_rows(rows: Vec<ScoreRow>, options: &OutputOptions) -> Vec<ScoreRow> {
+    let min_score = options.min_score.unwrap_or(f64::NEG_INFINITY);
+    let top_n = options.top_n.unwrap_or(usize::MAX);
+
+    rows.into_iter()
+        .filter(|row| row.score >= min_score)
+        .take(top_n)
+        .collect()
+}
+
+pub fn row_to_json(row: &ScoreRow, columns: &[Column]) -> Value {
+    let mut object = Map::new();
+    object.insert("peer".to_string(), Value::from(row.peer.clone()));
+    for &column in columns {
+        object.insert(column.name().to_string(), row.value(column));
+    }
+    Value::Object(object)
+}
+
+// quote_field quotes a field that holds the delimiter, a quote or a line
+// break, doubling its quotes, so that any peer name survives a round trip.
+pub fn quote_field(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn format_delimited(rows: &[ScoreRow], options: &OutputOptions, delimiter: char) -> String {
+    let mut out = String::new();
+
+    if options.header {
+        let header: Vec<&str> = std::iter::once("peer")
+            .chain(options.columns.iter().map(|c| c.name()))
+            .collect();
+        out.push_str(&header.join(&delimiter.to_string()));
+        out.push('\n');
+    }
+
+    for row in rows {
+        out.push_str(&quote_field(&row.peer, delimiter));
+        for &column in &options.columns {
+            out.push(delimiter);
+            out.push_str(&row.text(column));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+// format_scores renders (peer, score) pairs as returned by calculate_from_csv
+// in the requested format, applying the top-N and minimum score filters.
+pub fn format_scores(scores: &[(String, f64)], options: &OutputOptions) -> Result<String, String> {
+    format_scores_with_metadata(scores, options, None)
+}
+
+// format_scores_with_metadata is format_scores with run metadata, which is
+// embedded under a "metadata" key in the json format and ignored otherwise.
+pub fn format_scores_with_metadata(
+    scores: &[(String, f64)],
+    options: &OutputOptions,
+    metadata: Option<&Value>,
+) -> Result<String, String> {
+    let rows = filter_rows(rank_scores(scores), options);
+
+    match options.format {
+        OutputFormat::Csv => Ok(format_delimited(&rows, options, ',')),
+        OutputFormat::Tsv => Ok(format_delimited(&rows, options, '\t')),
+        OutputFormat::Json => {
+            let scores: Vec<Value> = rows
+                .iter()
+                .map(|row| row_to_json(row, &options.columns))
+                .collect();
+            let mut object = Map::new();
+            if let Some(metadata) = metadata {
+                object.insert("metadata".to_string(), metadata.clone());
+            }
+            object.insert("scores".to_string(), Value::Array(scores));
+            let json = serde_json::to_string(&Value::Object(object)).map_err(|e| e.to_string())?;
+            Ok(json + "\n")
+        }
+        OutputFormat::Jsonl => {
+            let mut out = String::new();
+            for row in &rows {
+                let line = serde_json::to_string(&row_to_json(row, &options.columns))
+                    .map_err(|e| e.to_string())?;
+                out.push_str(&line);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores() -> Vec<(String, f64)> {
+        vec![
+            ("bob".to_string(), 0.25),
+            ("alice".to_string(), 0.5),
+            ("carol".to_string(), 0.25),
+            ("dave".to_string(), 0.0),
+        ]
+    }
+
+    #[test]
+    fn test_rank_scores() {
+        let rows = rank_scores(&scores());
+
+        let ranks: Vec<(&str, usize)> = rows.iter().map(|r| (r.peer.as_str(), r.rank)).collect();
+        assert_eq!(ranks, vec![("alice", 1), ("bob", 2), ("carol", 2), ("dave", 4)]);
+
+        assert_eq!(rows[0].percentile, 100.0);
+        assert_eq!(rows[1].percentile, 75.0);
+        assert_eq!(rows[2].percentile, 75.0);
+        assert_eq!(rows[3].percentile, 25.0);
+
+        assert_eq!(rows[0].scaled, 1.0);
+        assert_eq!(rows[1].scaled, 0.5);
+        assert_eq!(rows[0].log_score, Some(0.5f64.log10()));
+        assert_eq!(rows[3].log_score, None);
+
+        // The same total order as engine::named_scores: NaN first, 0 before -0.
+        let odd = vec![
+            ("zero".to_string(), -0.0),
+            ("one".to_string(), 1.0),
+            ("nan".to_string(), f64::NAN),
+            ("plus_zero".to_string(), 0.0),
+        ];
+        let order: Vec<String> = rank_scores(&odd).into_iter().map(|r| r.peer).collect();
+        assert_eq!(order, vec!["nan", "one", "plus_zero", "zero"]);
+    }
+
+    #[test]
+    fn test_format_scores() {
+        let options = OutputOptions {
+            columns: vec![Column::Rank, Column::Score],
+            header: true,
+            top_n: Some(2),
+            ..OutputOptions::default()
+        };
+        let csv = format_scores(&scores(), &options).unwrap();
+        assert_eq!(csv, "peer,rank,score\nalice,1,0.5\nbob,2,0.25\n");
+
+        let options = OutputOptions {
+            format: OutputFormat::Tsv,
+            min_score: Some(0.3),
+            ..OutputOptions::default()
+        };
+        let tsv = format_scores(&scores(), &options).unwrap();
+        assert_eq!(tsv, "alice\t0.5\n");
+
+        let options = OutputOptions {
+            format: OutputFormat::Jsonl,
+            columns: vec![Column::Percentile, Column::LogScore],
+            min_score: Some(0.0),
+            ..OutputOptions::default()
+        };
+        let jsonl = format_scores(&scores(), &options).unwrap();
+        let last: Value = serde_json::from_str(jsonl.lines().last().unwrap()).unwrap();
+        assert_eq!(last["peer"], "dave");
+        assert_eq!(last["percentile"], 25.0);
+        assert!(last["log_score"].is_null());
+
+        let options = OutputOptions {
+            format: OutputFormat::Json,
+            columns: vec![Column::Rank],
+            ..OutputOptions::default()
+        };
+        let json: Value = serde_json::from_str(&format_scores(&scores(), &options).unwrap()).unwrap();
+        assert_eq!(json["scores"].as_array().unwrap().len(), 4);
+        assert_eq!(json["scores"][3]["rank"], 4);
+    }
+
+    #[test]
+    fn test_quoted_peers() {
+        let scores = vec![
+            ("a,b".to_string(), 0.5),
+            ("say \"hi\"".to_string(), 0.3),
+            ("two\nlines".to_string(), 0.2),
+        ];
+        let csv = format_scores(&scores, &OutputOptions::default()).unwrap();
+        assert_eq!(
+            csv,
+            "\"a,b\",0.5\n\"say \"\"hi\"\"\",0.3\n\"two\nlines\",0.2\n"
+        );
+
+        let options = OutputOptions {
+            format: OutputFormat::Tsv,
+            ..OutputOptions::default()
+        };
+        let tsv = format_scores(&scores, &options).unwrap();
+        assert!(tsv.starts_with("a,b\t0.5\n"));
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("rank, score,log").unwrap(),
+            vec![Column::Rank, Column::Score, Column::LogScore]
+        );
+        assert!(parse_columns("rank,bogus").is_err());
+        assert_eq!("NDJSON".parse::<OutputFormat>().unwrap(), OutputFormat::Jsonl);
+    }
+}
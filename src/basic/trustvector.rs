@@ -1,8 +1,6 @@
 use crate::sparse::entry::Entry;
 use crate::sparse::vector::Vector;
 use std::collections::{HashMap, HashSet};
-use std::error::Error;
-use std::fmt;
 
 // CanonicalizeTrustVector canonicalizes the trust vector in-place,
 // scaling it so that the elements sum to one,
@@ -20,6 +18,7 @@ pub fn canonicalize_trust_vector(v: &mut Vector) {
 
 // Helper function to canonicalize a vector in-place.
 // Returns an error if the vector is a zero vector.
+#[allow(clippy::ptr_arg)]
 fn canonicalize(entries: &mut Vec<Entry>) -> Result<(), &'static str> {
     let sum: f64 = entries.iter().map(|entry| entry.value).sum();
 
@@ -34,6 +33,7 @@ fn canonicalize(entries: &mut Vec<Entry>) -> Result<(), &'static str> {
     Ok(())
 }
 
+#[allow(dead_code)]
 enum DuplicateHandling {
     Allow,
     Remove,
@@ -49,7 +49,6 @@ pub fn read_trust_vector_from_csv(
     let mut max_peer = -1;
     let mut entries = Vec::new();
     let mut seen_peers = HashSet::new();
-    let remove_dublicates = true;
     let duplicate_handling = DuplicateHandling::Allow;
     let mut dublicate_count = 0;
 
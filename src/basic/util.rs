@@ -1,29 +1,81 @@
-use log::Level;
 use std::collections::HashMap;
 
 #[cfg(target_arch = "wasm32")]
-pub fn current_time_millis() -> u64 {
-    0 // todo
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    // The global performance object exists in windows, workers and Node.
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub fn current_time_millis() -> u64 {
-    // todo
-    use std::time::Instant;
-    Instant::now().elapsed().as_millis() as u64
+// Timer measures monotonic wall-clock time since it was started, using
+// performance.now() in wasm and Instant natively.
+#[derive(Debug, Clone, Copy)]
+pub struct Timer {
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
 }
 
-pub fn init_logger() {
+impl Timer {
+    #[cfg(target_arch = "wasm32")]
+    pub fn start() -> Self {
+        Timer {
+            start: performance_now(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start() -> Self {
+        Timer {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn elapsed_ms(&self) -> f64 {
+        performance_now() - self.start
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn elapsed_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+
+    // lap returns the elapsed time and restarts the timer.
+    pub fn lap(&mut self) -> f64 {
+        let elapsed = self.elapsed_ms();
+        *self = Timer::start();
+        elapsed
+    }
+}
+
+// init_logger routes log records to the browser console in wasm and to stderr
+// natively, keeping stdout for results. An explicit level wins; natively RUST_LOG
+// is used next, and info is the default otherwise.
+pub fn init_logger(level: Option<log::LevelFilter>) {
     #[cfg(target_arch = "wasm32")]
     {
-        console_log::init_with_level(Level::Trace).expect("Failed to initialize logger");
+        // console_log cannot be reinitialized, so it always accepts everything
+        // and the effective level is controlled with log::set_max_level.
+        if console_log::init_with_level(log::Level::Trace).is_err() {
+            log::warn!("Logger already initialized");
+        }
+        log::set_max_level(level.unwrap_or(log::LevelFilter::Info));
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        env_logger::builder()
-            .filter_level(log::LevelFilter::Trace)
-            .init();
+        let mut builder =
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+        if let Some(level) = level {
+            builder.filter_level(level);
+        }
+        builder.target(env_logger::Target::Stderr);
+        if builder.try_init().is_err() {
+            log::warn!("Logger already initialized");
+        }
         log::debug!("Logger initialized for native environment");
     }
 }
@@ -34,6 +86,12 @@ pub struct PeersMap {
     pub max_value: usize,
 }
 
+impl Default for PeersMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PeersMap {
     pub fn new() -> Self {
         PeersMap {
@@ -60,7 +118,7 @@ impl PeersMap {
     }
 }
 
-pub fn strip_headers<'a>(csv_content: &'a str) -> &'a str {
+pub fn strip_headers(csv_content: &str) -> &str {
     let mut lines = csv_content.lines();
 
     if let Some(first_line) = lines.next() {
@@ -0,0 +1,236 @@
+use super::localtrust::{parse_local_trust_record, read_local_trust_from_csv};
+use super::trustvector::read_trust_vector_from_csv;
+use super::util::strip_headers;
+use crate::graph::reachability::reachability;
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub local_trust_records: usize,
+    pub pretrust_records: usize,
+    pub peers: usize,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// Number of the first data line, so that reported line numbers match the file.
+fn first_line_number(csv_content: &str) -> usize {
+    if strip_headers(csv_content).len() == csv_content.len() {
+        1
+    } else {
+        2
+    }
+}
+
+fn parse_level(field: Option<&&str>) -> Result<f64, String> {
+    match field {
+        None => Ok(1.0),
+        Some(s) => {
+            let level = s
+                .parse::<f64>()
+                .map_err(|_| format!("invalid trust level {:?}", s))?;
+            if !level.is_finite() {
+                return Err(format!("trust level {:?} is not finite", s));
+            }
+            Ok(level)
+        }
+    }
+}
+
+// validate_csv checks local trust and pretrust CSV inputs the same way
+// calculate_from_csv reads them, but collects every problem instead of
+// stopping at the first one. Errors make the compute fail; warnings point
+// at data that is accepted but probably not intended.
+pub fn validate_csv(localtrust_csv: &str, pretrust_csv: &str) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut peers: HashSet<&str> = HashSet::new();
+    let mut edges: HashSet<(&str, &str)> = HashSet::new();
+
+    let first_line = first_line_number(localtrust_csv);
+    for (count, line) in strip_headers(localtrust_csv).lines().enumerate() {
+        let line_number = count + first_line;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match parse_local_trust_record(line) {
+            Ok(record) => record,
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("local trust line {}: {}", line_number, e));
+                continue;
+            }
+        };
+        let (truster, trustee, level) = (record.truster, record.trustee, record.level);
+
+        report.local_trust_records += 1;
+        peers.insert(truster);
+        peers.insert(trustee);
+
+        if truster == trustee {
+            report.warnings.push(format!(
+                "local trust line {}: self-trust {:?} -> {:?}",
+                line_number, truster, trustee
+            ));
+        }
+        if level < 0.0 {
+            report.warnings.push(format!(
+                "local trust line {}: negative trust {} is treated as distrust",
+                line_number, level
+            ));
+        }
+        if level == 0.0 {
+            report
+                .warnings
+                .push(format!("local trust line {}: zero trust is ignored", line_number));
+        }
+        if !edges.insert((truster, trustee)) {
+            report.warnings.push(format!(
+                "local trust line {}: duplicate edge {:?} -> {:?}",
+                line_number, truster, trustee
+            ));
+        }
+    }
+
+    if report.local_trust_records == 0 && report.errors.is_empty() {
+        report.errors.push("local trust is empty".to_string());
+    }
+    report.peers = peers.len();
+
+    let mut pretrust_sum = 0.0;
+    let mut seen_pretrust: HashSet<&str> = HashSet::new();
+
+    let first_line = first_line_number(pretrust_csv);
+    for (count, line) in strip_headers(pretrust_csv).lines().enumerate() {
+        let line_number = count + first_line;
+        let fields: Vec<&str> = line.split(',').collect();
+
+        if !peers.contains(fields[0]) {
+            report.errors.push(format!(
+                "pretrust line {}: peer {:?} does not appear in local trust",
+                line_number, fields[0]
+            ));
+            continue;
+        }
+
+        let level = match parse_level(fields.get(1)) {
+            Ok(level) => level,
+            Err(e) => {
+                report.errors.push(format!("pretrust line {}: {}", line_number, e));
+                continue;
+            }
+        };
+
+        report.pretrust_records += 1;
+        pretrust_sum += level;
+
+        if level < 0.0 {
+            report.warnings.push(format!(
+                "pretrust line {}: negative pretrust {}",
+                line_number, level
+            ));
+        }
+        if !seen_pretrust.insert(fields[0]) {
+            report.warnings.push(format!(
+                "pretrust line {}: duplicate peer {:?}",
+                line_number, fields[0]
+            ));
+        }
+    }
+
+    if report.pretrust_records > 0 && pretrust_sum == 0.0 {
+        report
+            .warnings
+            .push("pretrust sums to zero, a uniform pretrust will be used".to_string());
+    }
+
+    if report.is_valid() && pretrust_sum > 0.0 {
+        reachability_warnings(localtrust_csv, pretrust_csv, &mut report);
+    }
+
+    report
+}
+
+// Unreachable peers named in a warning before the rest are only counted.
+const MAX_NAMED_PEERS: usize = 10;
+
+fn reachability_warnings(localtrust_csv: &str, pretrust_csv: &str, report: &mut ValidationReport) {
+    let parsed = read_local_trust_from_csv(strip_headers(localtrust_csv)).and_then(
+        |(local_trust, peers)| {
+            let pre_trust = read_trust_vector_from_csv(strip_headers(pretrust_csv), &peers.map)?;
+            Ok(reachability(&local_trust, &pre_trust, &peers))
+        },
+    );
+    let reachability = match parsed {
+        Ok(reachability) => reachability,
+        Err(_) => return,
+    };
+
+    let unreachable = &reachability.unreachable;
+    if !unreachable.is_empty() {
+        let mut names = unreachable[..unreachable.len().min(MAX_NAMED_PEERS)].join(", ");
+        if unreachable.len() > MAX_NAMED_PEERS {
+            names.push_str(", ...");
+        }
+        report.warnings.push(format!(
+            "{} of {} peers are unreachable from pretrust and will score zero: {}",
+            unreachable.len(),
+            reachability.peers,
+            names
+        ));
+    }
+    for peer in &reachability.disconnected_seeds {
+        report.warnings.push(format!(
+            "pretrusted peer {:?} trusts no other peer",
+            peer
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_csv() {
+        let localtrust_csv = "i,j,v\nalice,bob,1\nbob,alice,x\nbob\nalice,alice,2\n\nalice,bob,-1";
+        let pretrust_csv = "alice,1\ncarol,1\nalice,0.5";
+
+        let report = validate_csv(localtrust_csv, pretrust_csv);
+
+        assert!(!report.is_valid());
+        assert_eq!(report.local_trust_records, 3);
+        assert_eq!(report.pretrust_records, 2);
+        assert_eq!(report.peers, 2);
+        assert_eq!(
+            report.errors,
+            vec![
+                "local trust line 3: invalid trust level \"x\"",
+                "local trust line 4: too few fields",
+                "pretrust line 2: peer \"carol\" does not appear in local trust",
+            ]
+        );
+        assert_eq!(report.warnings.len(), 4);
+
+        let report = validate_csv("alice,bob,1\nbob,alice,1", "alice,1");
+        assert!(report.is_valid());
+        assert!(report.warnings.is_empty());
+
+        let report = validate_csv("alice,bob,1\ncarol,dave,1", "alice,1\nbob,1");
+        assert!(report.is_valid());
+        assert_eq!(
+            report.warnings,
+            vec![
+                "2 of 4 peers are unreachable from pretrust and will score zero: carol, dave",
+                "pretrusted peer \"bob\" trusts no other peer",
+            ]
+        );
+    }
+}
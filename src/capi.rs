@@ -0,0 +1,581 @@
+// C ABI over basic::engine::TrustEngine; include/eigentrust.h is generated
+// from this file by build.rs with the capi feature.
+//
+// Functions returning int return 0 on success and -1 on error, with the
+// message available from et_graph_last_error. Integer peer IDs are the
+// same peers as their decimal strings.
+
+use crate::basic::decay::Decay;
+use crate::basic::engine::{ComputeOptions, DistrustPolicy, TrustEngine};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtDistrust {
+    EtDistrustIgnore = 0,
+    EtDistrustDiscount = 1,
+}
+
+// C enum constants are not scoped, so the variants carry the type name.
+#[allow(clippy::enum_variant_names)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtDecay {
+    EtDecayNone = 0,
+    EtDecayExponential = 1,
+    EtDecayLinear = 2,
+    EtDecayCutoff = 3,
+}
+
+/// EtOptions mirrors ComputeOptions. Zero epsilon and iteration counts mean
+/// the defaults. decay_duration is the half-life, window or maximum age in
+/// seconds, and applies to edges added with a timestamp; a zero
+/// decay_reference means the newest timestamp.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EtOptions {
+    pub alpha: f64,
+    pub epsilon: f64,
+    pub max_iterations: usize,
+    pub min_iterations: usize,
+    pub distrust: EtDistrust,
+    pub prune_unreachable: bool,
+    pub include_unreachable: bool,
+    pub decay: EtDecay,
+    pub decay_duration: f64,
+    pub decay_reference: f64,
+}
+
+impl From<&EtOptions> for ComputeOptions {
+    fn from(options: &EtOptions) -> Self {
+        let optional = |n: usize| if n == 0 { None } else { Some(n) };
+        ComputeOptions {
+            alpha: options.alpha,
+            epsilon: if options.epsilon > 0.0 {
+                Some(options.epsilon)
+            } else {
+                None
+            },
+            max_iterations: optional(options.max_iterations),
+            min_iterations: optional(options.min_iterations),
+            distrust: match options.distrust {
+                EtDistrust::EtDistrustIgnore => DistrustPolicy::Ignore,
+                EtDistrust::EtDistrustDiscount => DistrustPolicy::Discount,
+            },
+            prune_unreachable: options.prune_unreachable,
+            include_unreachable: options.include_unreachable,
+            decay: match options.decay {
+                EtDecay::EtDecayNone => None,
+                EtDecay::EtDecayExponential => Some(Decay::Exponential {
+                    half_life: options.decay_duration,
+                }),
+                EtDecay::EtDecayLinear => Some(Decay::Linear {
+                    window: options.decay_duration,
+                }),
+                EtDecay::EtDecayCutoff => Some(Decay::Cutoff {
+                    max_age: options.decay_duration,
+                }),
+            },
+            decay_reference: if options.decay_reference != 0.0 {
+                Some(options.decay_reference)
+            } else {
+                None
+            },
+            ..ComputeOptions::default()
+        }
+    }
+}
+
+/// EtGraph is the opaque handle: the graph, pretrust, options and the
+/// scores of the last compute.
+pub struct EtGraph {
+    engine: TrustEngine,
+    pretrust: Vec<(String, f64)>,
+    options: ComputeOptions,
+    // None until computed and after edits.
+    scores: Option<Vec<(CString, f64)>>,
+    error: Option<CString>,
+}
+
+impl EtGraph {
+    fn status(&mut self, result: Result<(), String>) -> c_int {
+        match result {
+            Ok(()) => 0,
+            Err(e) => {
+                // Interior NULs cannot be represented, so drop them.
+                self.error = Some(CString::new(e.replace('\0', "")).unwrap_or_default());
+                -1
+            }
+        }
+    }
+
+    fn set_edge(&mut self, from: &str, to: &str, level: f64, timestamp: Option<f64>) -> c_int {
+        let result = self.engine.set_edge_at(from, to, level, timestamp);
+        if result.is_ok() {
+            self.scores = None;
+        }
+        self.status(result)
+    }
+
+    fn set_pretrust(&mut self, peer: &str, level: f64) -> c_int {
+        let result = if level.is_finite() {
+            self.pretrust.retain(|(p, _)| p != peer);
+            if level != 0.0 {
+                self.pretrust.push((peer.to_string(), level));
+            }
+            self.scores = None;
+            Ok(())
+        } else {
+            Err(format!("Invalid trust level: {}", level))
+        };
+        self.status(result)
+    }
+}
+
+unsafe fn handle<'a>(graph: *mut EtGraph) -> Option<&'a mut EtGraph> {
+    graph.as_mut()
+}
+
+unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Null string".to_string());
+    }
+    CStr::from_ptr(s).to_str().map_err(|e| e.to_string())
+}
+
+#[no_mangle]
+pub extern "C" fn et_options_default() -> EtOptions {
+    EtOptions {
+        alpha: ComputeOptions::default().alpha,
+        epsilon: 0.0,
+        max_iterations: 0,
+        min_iterations: 0,
+        distrust: EtDistrust::EtDistrustIgnore,
+        prune_unreachable: false,
+        include_unreachable: false,
+        decay: EtDecay::EtDecayNone,
+        decay_duration: 0.0,
+        decay_reference: 0.0,
+    }
+}
+
+/// et_graph_new returns an empty graph to be freed with et_graph_free.
+#[no_mangle]
+pub extern "C" fn et_graph_new() -> *mut EtGraph {
+    Box::into_raw(Box::new(EtGraph {
+        engine: TrustEngine::new(),
+        pretrust: vec![],
+        options: ComputeOptions::default(),
+        scores: None,
+        error: None,
+    }))
+}
+
+/// # Safety
+/// `graph` must come from et_graph_new and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_free(graph: *mut EtGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// # Safety
+/// `graph` must be valid. The message is valid until the next call on the graph.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_last_error(graph: *const EtGraph) -> *const c_char {
+    match graph.as_ref().and_then(|graph| graph.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// et_graph_add_edge sets the trust level from one peer to another, adding
+/// unknown peers. A zero level removes the edge.
+///
+/// # Safety
+/// `graph` must be valid and the peers NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_add_edge(
+    graph: *mut EtGraph,
+    from: *const c_char,
+    to: *const c_char,
+    level: f64,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    match (string(from), string(to)) {
+        (Ok(from), Ok(to)) => graph.set_edge(from, to, level, None),
+        (Err(e), _) | (_, Err(e)) => graph.status(Err(e)),
+    }
+}
+
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_add_edge_id(
+    graph: *mut EtGraph,
+    from: u64,
+    to: u64,
+    level: f64,
+) -> c_int {
+    match handle(graph) {
+        Some(graph) => graph.set_edge(&from.to_string(), &to.to_string(), level, None),
+        None => -1,
+    }
+}
+
+/// et_graph_add_edge_at is et_graph_add_edge with the time of the edge in
+/// unix seconds, which decay weighs it by. Edges added without one are not
+/// decayed.
+///
+/// # Safety
+/// `graph` must be valid and the peers NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_add_edge_at(
+    graph: *mut EtGraph,
+    from: *const c_char,
+    to: *const c_char,
+    level: f64,
+    timestamp: f64,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    match (string(from), string(to)) {
+        (Ok(from), Ok(to)) => graph.set_edge(from, to, level, Some(timestamp)),
+        (Err(e), _) | (_, Err(e)) => graph.status(Err(e)),
+    }
+}
+
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_add_edge_id_at(
+    graph: *mut EtGraph,
+    from: u64,
+    to: u64,
+    level: f64,
+    timestamp: f64,
+) -> c_int {
+    match handle(graph) {
+        Some(graph) => graph.set_edge(&from.to_string(), &to.to_string(), level, Some(timestamp)),
+        None => -1,
+    }
+}
+
+/// et_graph_set_pretrust sets a peer's pretrust level; zero removes it.
+/// Levels are normalized on compute.
+///
+/// # Safety
+/// `graph` must be valid and `peer` NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_set_pretrust(
+    graph: *mut EtGraph,
+    peer: *const c_char,
+    level: f64,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    match string(peer) {
+        Ok(peer) => graph.set_pretrust(peer, level),
+        Err(e) => graph.status(Err(e)),
+    }
+}
+
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_set_pretrust_id(
+    graph: *mut EtGraph,
+    peer: u64,
+    level: f64,
+) -> c_int {
+    match handle(graph) {
+        Some(graph) => graph.set_pretrust(&peer.to_string(), level),
+        None => -1,
+    }
+}
+
+/// # Safety
+/// `graph` must be valid and `options` null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_set_options(
+    graph: *mut EtGraph,
+    options: *const EtOptions,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    match options.as_ref() {
+        Some(options) => {
+            // Keep the algorithm, which is set by name.
+            graph.options = ComputeOptions {
+                algorithm: graph.options.algorithm,
+                ..options.into()
+            };
+            0
+        }
+        None => graph.status(Err("Null options".to_string())),
+    }
+}
+
+/// et_graph_set_algorithm selects the algorithm by name: eigentrust (the
+/// default), pagerank, ppr or sybilrank.
+///
+/// # Safety
+/// `graph` must be valid and `name` NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_set_algorithm(graph: *mut EtGraph, name: *const c_char) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    let result = string(name).and_then(|name| {
+        graph.options.algorithm = name.parse()?;
+        Ok(())
+    });
+    graph.status(result)
+}
+
+/// et_graph_compute runs EigenTrust, keeping the scores sorted by descending score.
+///
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_compute(graph: *mut EtGraph) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    graph.scores = None;
+    let pretrust = graph
+        .pretrust
+        .iter()
+        .map(|(peer, level)| (peer.as_str(), *level));
+    let result = graph
+        .engine
+        .compute_with_pretrust(pretrust, &graph.options, None)
+        .map(|scores| {
+            graph.scores = Some(
+                scores
+                    .into_iter()
+                    .map(|(peer, score)| (CString::new(peer).unwrap_or_default(), score))
+                    .collect(),
+            );
+        });
+    graph.status(result)
+}
+
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_peer_count(graph: *const EtGraph) -> usize {
+    graph.as_ref().map_or(0, |graph| graph.engine.peer_count())
+}
+
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_edge_count(graph: *const EtGraph) -> usize {
+    graph.as_ref().map_or(0, |graph| graph.engine.edge_count())
+}
+
+/// et_graph_score_count is the number of scores from the last compute: the
+/// peers with a nonzero score, or every peer with include_unreachable. It is
+/// zero after the graph or pretrust was edited.
+///
+/// # Safety
+/// `graph` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_score_count(graph: *const EtGraph) -> usize {
+    graph
+        .as_ref()
+        .and_then(|graph| graph.scores.as_ref())
+        .map_or(0, Vec::len)
+}
+
+/// et_graph_score_at reads the index-th highest score. The peer string is
+/// valid until the next compute or edit.
+///
+/// # Safety
+/// `graph` must be valid; `peer` and `score` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_score_at(
+    graph: *const EtGraph,
+    index: usize,
+    peer: *mut *const c_char,
+    score: *mut f64,
+) -> c_int {
+    let Some((name, value)) = graph
+        .as_ref()
+        .and_then(|graph| graph.scores.as_ref()?.get(index)) else {
+        return -1;
+    };
+    if !peer.is_null() {
+        *peer = name.as_ptr();
+    }
+    if !score.is_null() {
+        *score = *value;
+    }
+    0
+}
+
+/// et_graph_score reads a peer's score from the last compute, zero for peers
+/// without one. It fails for unknown peers and after the graph or pretrust was edited.
+///
+/// # Safety
+/// `graph` must be valid, `peer` NUL-terminated UTF-8 and `score` valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_score(
+    graph: *mut EtGraph,
+    peer: *const c_char,
+    score: *mut f64,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    let result = string(peer).and_then(|peer| peer_score(graph, peer, score));
+    graph.status(result)
+}
+
+/// # Safety
+/// `graph` must be valid and `score` valid.
+#[no_mangle]
+pub unsafe extern "C" fn et_graph_score_id(
+    graph: *mut EtGraph,
+    peer: u64,
+    score: *mut f64,
+) -> c_int {
+    let Some(graph) = handle(graph) else {
+        return -1;
+    };
+    let result = peer_score(graph, &peer.to_string(), score);
+    graph.status(result)
+}
+
+unsafe fn peer_score(graph: &EtGraph, peer: &str, score: *mut f64) -> Result<(), String> {
+    if score.is_null() {
+        return Err("Null score".to_string());
+    }
+    if graph.scores.is_none() {
+        return Err("No scores, run et_graph_compute first".to_string());
+    }
+    let value = graph
+        .engine
+        .score(peer)
+        .ok_or_else(|| format!("No score for peer: {}", peer))?;
+    *score = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capi() {
+        unsafe {
+            let graph = et_graph_new();
+            let alice = c"alice";
+            let bob = c"bob";
+            assert_eq!(
+                et_graph_add_edge(graph, alice.as_ptr(), bob.as_ptr(), 1.0),
+                0
+            );
+            assert_eq!(et_graph_add_edge_id(graph, 2, 1, 1.0), 0);
+            assert_eq!(
+                et_graph_add_edge(graph, bob.as_ptr(), c"2".as_ptr(), 1.0),
+                0
+            );
+            assert_eq!(et_graph_peer_count(graph), 4);
+            assert_eq!(et_graph_edge_count(graph), 3);
+
+            // Pretrust on a peer outside the graph fails.
+            assert_eq!(et_graph_set_pretrust_id(graph, 7, 1.0), 0);
+            assert_eq!(et_graph_compute(graph), -1);
+            let error = CStr::from_ptr(et_graph_last_error(graph));
+            assert_eq!(error.to_str().unwrap(), "Invalid peer: 7");
+
+            assert_eq!(et_graph_set_pretrust_id(graph, 7, 0.0), 0);
+            assert_eq!(et_graph_set_pretrust(graph, alice.as_ptr(), 1.0), 0);
+            let options = EtOptions {
+                alpha: 0.2,
+                ..et_options_default()
+            };
+            assert_eq!(et_graph_set_options(graph, &options), 0);
+            assert_eq!(et_graph_compute(graph), 0);
+
+            let mut engine = TrustEngine::from_edges(vec![
+                ("alice", "bob", 1.0),
+                ("2", "1", 1.0),
+                ("bob", "2", 1.0),
+            ])
+            .unwrap();
+            let expected = engine
+                .compute_with_pretrust(vec![("alice", 1.0)], &ComputeOptions::from(&options), None)
+                .unwrap();
+
+            assert_eq!(et_graph_score_count(graph), expected.len());
+            let mut peer = ptr::null();
+            let mut score = 0.0;
+            assert_eq!(et_graph_score_at(graph, 0, &mut peer, &mut score), 0);
+            assert_eq!(CStr::from_ptr(peer).to_str().unwrap(), expected[0].0);
+            assert_eq!(score, expected[0].1);
+            assert_eq!(et_graph_score_id(graph, 1, &mut score), 0);
+            assert_eq!(Some(score), engine.score("1"));
+            assert_eq!(et_graph_score(graph, c"carol".as_ptr(), &mut score), -1);
+
+            // Changing the pretrust drops the scores until the next compute.
+            assert_eq!(et_graph_set_pretrust(graph, bob.as_ptr(), 1.0), 0);
+            assert_eq!(et_graph_score_count(graph), 0);
+            assert_eq!(et_graph_score(graph, alice.as_ptr(), &mut score), -1);
+            let error = CStr::from_ptr(et_graph_last_error(graph));
+            assert_eq!(error.to_str().unwrap(), "No scores, run et_graph_compute first");
+            assert_eq!(et_graph_score_id(graph, 1, &mut score), -1);
+            assert_eq!(et_graph_compute(graph), 0);
+            assert_eq!(et_graph_score(graph, alice.as_ptr(), &mut score), 0);
+
+            et_graph_free(graph);
+        }
+    }
+
+    #[test]
+    fn test_capi_decay() {
+        unsafe {
+            let graph = et_graph_new();
+            let alice = c"alice";
+            let year = 365.0 * 86400.0;
+            assert_eq!(et_graph_add_edge_at(graph, alice.as_ptr(), c"bob".as_ptr(), 1.0, 0.0), 0);
+            assert_eq!(
+                et_graph_add_edge_at(graph, alice.as_ptr(), c"carol".as_ptr(), 1.0, year),
+                0
+            );
+            assert_eq!(et_graph_set_pretrust(graph, alice.as_ptr(), 1.0), 0);
+            let options = EtOptions {
+                decay: EtDecay::EtDecayCutoff,
+                decay_duration: 86400.0,
+                ..et_options_default()
+            };
+            assert_eq!(et_graph_set_options(graph, &options), 0);
+            assert_eq!(et_graph_compute(graph), 0);
+
+            // bob's edge is older than the cutoff.
+            let mut score = 0.0;
+            assert_eq!(et_graph_score(graph, c"bob".as_ptr(), &mut score), 0);
+            assert_eq!(score, 0.0);
+            assert_eq!(et_graph_score(graph, c"carol".as_ptr(), &mut score), 0);
+            assert!(score > 0.0);
+
+            let options = EtOptions {
+                decay_duration: 0.0,
+                ..options
+            };
+            assert_eq!(et_graph_set_options(graph, &options), 0);
+            assert_eq!(et_graph_compute(graph), -1);
+
+            et_graph_free(graph);
+        }
+    }
+}
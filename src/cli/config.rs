@@ -0,0 +1,152 @@
+use crate::basic::engine::ComputeOptions;
+use crate::basic::events::EventPolicy;
+use crate::basic::output::{OutputFormat, OutputOptions};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// RunConfig describes a complete compute job. It can be loaded from a TOML or
+// JSON file and is then overridden by command line flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    pub localtrust: Option<PathBuf>,
+    pub pretrust: Option<PathBuf>,
+    // Format of both input files; csv if omitted.
+    pub input_format: Option<OutputFormat>,
+    pub compute: ComputeOptions,
+    pub output: OutputOptions,
+    // Where scores are written; stdout if omitted.
+    pub output_path: Option<PathBuf>,
+    // Where the run metadata (including this config) is written as JSON.
+    pub metadata_path: Option<PathBuf>,
+}
+
+// parse_config_file parses JSON if the path ends in .json and TOML otherwise.
+pub fn parse_config_file<T: DeserializeOwned>(content: &str, path: &Path) -> Result<T, String> {
+    let is_json = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    if is_json {
+        serde_json::from_str(content).map_err(|e| format!("{}: {}", path.display(), e))
+    } else {
+        toml::from_str(content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl RunConfig {
+    pub fn parse(content: &str, path: &Path) -> Result<RunConfig, String> {
+        parse_config_file(content, path)
+    }
+
+    // load reads a config file; relative paths in it are resolved against
+    // the directory of the config file so that jobs can be rerun from anywhere.
+    pub fn load(path: &Path) -> Result<RunConfig, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut config = RunConfig::parse(&content, path)?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for p in [
+            &mut config.localtrust,
+            &mut config.pretrust,
+            &mut config.output_path,
+            &mut config.metadata_path,
+        ] {
+            if let Some(relative) = p.as_ref().filter(|p| p.is_relative()) {
+                *p = Some(base.join(relative));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+// load_event_policy reads an ingest weighting policy from a TOML or JSON file.
+// Event types are lowercased, as --weight and the events are.
+pub fn load_event_policy(path: &Path) -> Result<EventPolicy, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut policy: EventPolicy = parse_config_file(&content, path)?;
+    policy
+        .lowercase_weights()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::engine::DistrustPolicy;
+    use crate::basic::events::Aggregation;
+    use crate::basic::output::Column;
+
+    #[test]
+    fn test_parse_config() {
+        let toml_config = r#"
+            localtrust = "localtrust.csv"
+            pretrust = "pretrust.csv"
+
+            [compute]
+            alpha = 0.2
+            max_iterations = 100
+            distrust = "discount"
+
+            [output]
+            format = "jsonl"
+            columns = ["rank", "score"]
+            top_n = 10
+        "#;
+        let config = RunConfig::parse(toml_config, Path::new("run.toml")).unwrap();
+
+        assert_eq!(config.localtrust, Some(PathBuf::from("localtrust.csv")));
+        assert_eq!(config.compute.alpha, 0.2);
+        assert_eq!(config.compute.max_iterations, Some(100));
+        assert_eq!(config.compute.epsilon, None);
+        assert_eq!(config.compute.distrust, DistrustPolicy::Discount);
+        assert_eq!(config.output.format, OutputFormat::Jsonl);
+        assert_eq!(config.output.columns, vec![Column::Rank, Column::Score]);
+        assert_eq!(config.output.top_n, Some(10));
+        assert!(!config.output.header);
+
+        let json_config = r#"{"compute": {"alpha": 0.2, "max_iterations": 100, "distrust": "discount"}}"#;
+        let config = RunConfig::parse(json_config, Path::new("run.json")).unwrap();
+        assert_eq!(config.compute.alpha, 0.2);
+        assert_eq!(config.output, OutputOptions::default());
+
+        assert!(RunConfig::parse("alpha = 0.2", Path::new("run.toml")).is_err());
+        // Typos in nested tables are errors too.
+        assert!(RunConfig::parse("[compute]\nalpah = 0.2", Path::new("run.toml")).is_err());
+        assert!(RunConfig::parse("[output]\ntop = 5", Path::new("run.toml")).is_err());
+    }
+
+    #[test]
+    fn test_parse_event_policy() {
+        let toml_policy = "aggregation = \"log_sum\"\n[weights]\nFollow = 2.0";
+        let policy: EventPolicy = parse_config_file(toml_policy, Path::new("policy.toml")).unwrap();
+        assert_eq!(policy.weights.get("Follow"), Some(&2.0));
+        let policy: EventPolicy =
+            parse_config_file(r#"{"weights": {"like": 0.5}}"#, Path::new("policy.json")).unwrap();
+        assert_eq!(policy.weights.get("like"), Some(&0.5));
+        assert!(parse_config_file::<EventPolicy>("weight = 1", Path::new("policy.toml")).is_err());
+        // The CLI writes cap:10; files use the serde form.
+        let policy: EventPolicy =
+            parse_config_file("aggregation = { cap = 10 }", Path::new("policy.toml")).unwrap();
+        assert_eq!(policy.aggregation, Aggregation::Cap(10.0));
+        let policy: EventPolicy =
+            parse_config_file(r#"{"aggregation": {"cap": 10}}"#, Path::new("policy.json")).unwrap();
+        assert_eq!(policy.aggregation, Aggregation::Cap(10.0));
+
+        let dir = std::env::temp_dir().join(format!("eigentrust-policy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.toml");
+        fs::write(&path, "[weights]\nFollow = 2.0").unwrap();
+        assert_eq!(load_event_policy(&path).unwrap().weights.get("follow"), Some(&2.0));
+        fs::write(&path, "[weights]\nFollow = 2.0\nfollow = 1.0").unwrap();
+        assert!(load_event_policy(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
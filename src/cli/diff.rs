@@ -0,0 +1,167 @@
+use super::records::Records;
+use crate::basic::output::rank_scores;
+use serde::Serialize;
+use std::cmp;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoreDiff {
+    pub peer: String,
+    pub score_a: Option<f64>,
+    pub score_b: Option<f64>,
+    pub rank_a: Option<usize>,
+    pub rank_b: Option<usize>,
+    // score_b - score_a, missing scores count as zero.
+    pub delta: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffSummary {
+    pub peers_a: usize,
+    pub peers_b: usize,
+    pub only_in_a: usize,
+    pub only_in_b: usize,
+    pub changed: usize,
+    pub l1_distance: f64,
+    pub max_abs_delta: f64,
+}
+
+// read_scores takes the peer from a "peer" or "i" column and the score from a
+// "score" or "v" column, falling back to the first two columns.
+pub fn read_scores(records: &Records) -> Result<Vec<(String, f64)>, String> {
+    let peer = records.column(&["peer", "i"]).unwrap_or(0);
+    let score = records.column(&["score", "v"]).unwrap_or(1);
+
+    records
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let name = row
+                .get(peer)
+                .ok_or_else(|| format!("record #{}: missing peer", i + 1))?;
+            let value = row
+                .get(score)
+                .and_then(|v| v.parse::<f64>().ok())
+                .ok_or_else(|| format!("record #{}: missing or invalid score", i + 1))?;
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+// diff_scores compares two score lists peer by peer. Rows are sorted by
+// the absolute score change, largest first.
+pub fn diff_scores(a: &[(String, f64)], b: &[(String, f64)]) -> (Vec<ScoreDiff>, DiffSummary) {
+    let ranked_a: HashMap<String, (f64, usize)> = rank_scores(a)
+        .into_iter()
+        .map(|r| (r.peer, (r.score, r.rank)))
+        .collect();
+    let ranked_b: HashMap<String, (f64, usize)> = rank_scores(b)
+        .into_iter()
+        .map(|r| (r.peer, (r.score, r.rank)))
+        .collect();
+
+    let mut peers: Vec<&String> = ranked_a.keys().chain(ranked_b.keys()).collect();
+    peers.sort();
+    peers.dedup();
+
+    let mut summary = DiffSummary {
+        peers_a: ranked_a.len(),
+        peers_b: ranked_b.len(),
+        only_in_a: 0,
+        only_in_b: 0,
+        changed: 0,
+        l1_distance: 0.0,
+        max_abs_delta: 0.0,
+    };
+
+    let mut rows: Vec<ScoreDiff> = peers
+        .into_iter()
+        .map(|peer| {
+            let a = ranked_a.get(peer);
+            let b = ranked_b.get(peer);
+            let delta = b.map_or(0.0, |x| x.0) - a.map_or(0.0, |x| x.0);
+
+            match (a, b) {
+                (Some(_), None) => summary.only_in_a += 1,
+                (None, Some(_)) => summary.only_in_b += 1,
+                _ => {}
+            }
+            if delta != 0.0 {
+                summary.changed += 1;
+            }
+            summary.l1_distance += delta.abs();
+            summary.max_abs_delta = summary.max_abs_delta.max(delta.abs());
+
+            ScoreDiff {
+                peer: peer.clone(),
+                score_a: a.map(|x| x.0),
+                score_b: b.map(|x| x.0),
+                rank_a: a.map(|x| x.1),
+                rank_b: b.map(|x| x.1),
+                delta,
+            }
+        })
+        .collect();
+
+    rows.sort_by(|x, y| {
+        y.delta
+            .abs()
+            .partial_cmp(&x.delta.abs())
+            .unwrap_or(cmp::Ordering::Equal)
+    });
+
+    (rows, summary)
+}
+
+pub fn diff_to_records(rows: &[ScoreDiff]) -> Records {
+    let optional = |v: Option<String>| v.unwrap_or_default();
+    Records {
+        header: ["peer", "score_a", "score_b", "rank_a", "rank_b", "delta"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect(),
+        rows: rows
+            .iter()
+            .map(|r| {
+                vec![
+                    r.peer.clone(),
+                    optional(r.score_a.map(|v| v.to_string())),
+                    optional(r.score_b.map(|v| v.to_string())),
+                    optional(r.rank_a.map(|v| v.to_string())),
+                    optional(r.rank_b.map(|v| v.to_string())),
+                    r.delta.to_string(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_scores() {
+        let a = vec![("alice".to_string(), 0.5), ("bob".to_string(), 0.5)];
+        let b = vec![
+            ("alice".to_string(), 0.75),
+            ("carol".to_string(), 0.25),
+        ];
+
+        let (rows, summary) = diff_scores(&a, &b);
+
+        assert_eq!(rows[0].peer, "bob");
+        assert_eq!(rows[0].delta, -0.5);
+        assert_eq!(rows[0].score_b, None);
+        assert_eq!(rows[1].peer, "alice");
+        assert_eq!(rows[1].rank_a, Some(1));
+        assert_eq!(rows[1].rank_b, Some(1));
+
+        assert_eq!(summary.only_in_a, 1);
+        assert_eq!(summary.only_in_b, 1);
+        assert_eq!(summary.changed, 3);
+        assert_eq!(summary.l1_distance, 1.0);
+        assert_eq!(summary.max_abs_delta, 0.5);
+    }
+}
@@ -0,0 +1,935 @@
+pub mod config;
+pub mod diff;
+pub mod records;
+
+use crate::basic::algorithm::Algorithm;
+use crate::basic::attack::{simulate_attack, Attack};
+use crate::basic::decay::Decay;
+use crate::basic::engine::{
+    calculate_from_csv_with_timings, ComputeOptions, DistrustPolicy, TrustEngine,
+};
+use crate::basic::events::{
+    derive_local_trust, local_trust_to_csv, parse_events_csv, Aggregation, EventPolicy,
+};
+use crate::basic::localtrust::{read_local_trust_from_csv, read_timestamped_local_trust_from_csv};
+use crate::basic::output::{format_scores_with_metadata, parse_columns, OutputFormat, OutputOptions};
+use crate::basic::util::strip_headers;
+use crate::basic::validate::validate_csv;
+use crate::graph::collusion::CollusionOptions;
+use crate::graph::generate::SyntheticGraph;
+use crate::graph::stats::graph_stats;
+use crate::server::serve;
+use clap::{Args, Parser, Subcommand};
+use config::{load_event_policy, RunConfig};
+use log::LevelFilter;
+use records::{format_from_path, read_records, write_records};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+#[command(name = "eigentrust", version, about = "EigenTrust over local trust and pretrust CSV files")]
+pub struct Cli {
+    /// Log level: off, error, warn, info, debug or trace. Overrides RUST_LOG; logs go to stderr.
+    #[arg(long, global = true)]
+    pub log_level: Option<LevelFilter>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compute global trust scores.
+    Compute(ComputeArgs),
+    /// Check inputs and report problems without computing.
+    Validate(InputArgs),
+    /// Print local trust graph statistics.
+    Stats(StatsArgs),
+    /// Convert a local trust, pretrust or score file between formats.
+    Convert(ConvertArgs),
+    /// Compare two score files.
+    Diff(DiffArgs),
+    /// Explain a peer's score: its pretrust share and what each truster contributes.
+    Explain(ExplainArgs),
+    /// Generate a synthetic local trust graph, optionally with attackers.
+    Generate(GenerateArgs),
+    /// Derive local trust from raw interaction events.
+    Ingest(IngestArgs),
+    /// Detect communities and list the top peers of each.
+    Communities(CommunitiesArgs),
+    /// Flag groups of peers that look like they trade trust among themselves.
+    Collusion(CollusionArgs),
+    /// Add an attack to a graph and report how much trust the attackers capture.
+    Simulate(SimulateArgs),
+    /// Serve the compute pipeline over HTTP.
+    Serve(ServeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// Local trust CSV (truster,trustee[,level]).
+    pub localtrust: PathBuf,
+    /// Pretrust CSV (peer[,level]).
+    pub pretrust: PathBuf,
+}
+
+// Compute and output flags are optional so that only the flags actually
+// given override the values from a config file.
+#[derive(Debug, Args)]
+pub struct ComputeOptionArgs {
+    /// Pretrust weight in [0, 1]; 0.5 by default.
+    #[arg(long)]
+    pub alpha: Option<f64>,
+    /// Convergence threshold; defaults to 1e-6 / number of peers.
+    #[arg(long)]
+    pub epsilon: Option<f64>,
+    #[arg(long)]
+    pub max_iterations: Option<usize>,
+    #[arg(long)]
+    pub min_iterations: Option<usize>,
+    /// What to do with negative local trust: ignore (default) or discount.
+    #[arg(long)]
+    pub distrust: Option<DistrustPolicy>,
+    /// Scoring algorithm: eigentrust (default), pagerank, ppr or sybilrank.
+    #[arg(long)]
+    pub algorithm: Option<Algorithm>,
+    /// Drop peers the pretrust cannot reach before computing.
+    #[arg(long, overrides_with = "no_prune_unreachable")]
+    pub prune_unreachable: bool,
+    /// Do not prune unreachable peers, even if the config does.
+    #[arg(long)]
+    pub no_prune_unreachable: bool,
+    /// List peers without a score with score zero.
+    #[arg(long, overrides_with = "no_include_unreachable")]
+    pub include_unreachable: bool,
+    /// Leave peers without a score out, even if the config includes them.
+    #[arg(long)]
+    pub no_include_unreachable: bool,
+    /// Weigh local trust by the age of its timestamp column (unix seconds):
+    /// exponential:<half-life>, linear:<window> or cutoff:<max age>, e.g. exponential:90d.
+    #[arg(long)]
+    pub decay: Option<Decay>,
+    /// Time that --decay measures ages from, in unix seconds; the newest timestamp by default.
+    #[arg(long)]
+    pub decay_reference: Option<f64>,
+}
+
+impl ComputeOptionArgs {
+    pub fn apply(&self, options: &mut ComputeOptions) {
+        if let Some(alpha) = self.alpha {
+            options.alpha = alpha;
+        }
+        if self.epsilon.is_some() {
+            options.epsilon = self.epsilon;
+        }
+        if self.max_iterations.is_some() {
+            options.max_iterations = self.max_iterations;
+        }
+        if self.min_iterations.is_some() {
+            options.min_iterations = self.min_iterations;
+        }
+        if let Some(distrust) = self.distrust {
+            options.distrust = distrust;
+        }
+        if let Some(algorithm) = self.algorithm {
+            options.algorithm = algorithm;
+        }
+        if self.prune_unreachable || self.no_prune_unreachable {
+            options.prune_unreachable = self.prune_unreachable;
+        }
+        if self.include_unreachable || self.no_include_unreachable {
+            options.include_unreachable = self.include_unreachable;
+        }
+        if self.decay.is_some() {
+            options.decay = self.decay;
+        }
+        if self.decay_reference.is_some() {
+            options.decay_reference = self.decay_reference;
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Output format: csv (default), tsv, json or jsonl.
+    #[arg(long)]
+    pub format: Option<OutputFormat>,
+    /// Comma separated columns: rank, score, percentile, scaled, log_score.
+    #[arg(long)]
+    pub columns: Option<String>,
+    /// Only output the N highest scores.
+    #[arg(long)]
+    pub top: Option<usize>,
+    /// Only output scores greater than or equal to this value.
+    #[arg(long)]
+    pub min_score: Option<f64>,
+    /// Do not write a header line, even if the config turns it on.
+    #[arg(long, overrides_with = "header")]
+    pub no_header: bool,
+    /// Write a header line for csv and tsv.
+    #[arg(long)]
+    pub header: bool,
+    /// Write to a file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Write run metadata, including the effective config, to a JSON file.
+    #[arg(long)]
+    pub metadata: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    pub fn apply(&self, options: &mut OutputOptions) -> Result<(), String> {
+        if let Some(format) = self.format {
+            options.format = format;
+        }
+        if let Some(columns) = &self.columns {
+            options.columns = parse_columns(columns)?;
+        }
+        if self.no_header || self.header {
+            options.header = self.header;
+        }
+        if self.top.is_some() {
+            options.top_n = self.top;
+        }
+        if self.min_score.is_some() {
+            options.min_score = self.min_score;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ComputeArgs {
+    /// Local trust file (truster,trustee[,level]); may be set in --config instead.
+    pub localtrust: Option<PathBuf>,
+    /// Pretrust file (peer[,level]); may be set in --config instead.
+    pub pretrust: Option<PathBuf>,
+    /// TOML or JSON run config; flags override its values.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Format of the input files: csv (default), tsv, json or jsonl.
+    #[arg(long)]
+    pub input_format: Option<OutputFormat>,
+    #[command(flatten)]
+    pub compute: ComputeOptionArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+impl ComputeArgs {
+    // effective_config merges the config file, if any, with the command line flags.
+    pub fn effective_config(&self) -> Result<RunConfig, String> {
+        let mut config = match &self.config {
+            Some(path) => RunConfig::load(path)?,
+            None => RunConfig::default(),
+        };
+
+        if self.localtrust.is_some() {
+            config.localtrust = self.localtrust.clone();
+        }
+        if self.pretrust.is_some() {
+            config.pretrust = self.pretrust.clone();
+        }
+        if self.input_format.is_some() {
+            config.input_format = self.input_format;
+        }
+        if self.output.output.is_some() {
+            config.output_path = self.output.output.clone();
+        }
+        if self.output.metadata.is_some() {
+            config.metadata_path = self.output.metadata.clone();
+        }
+        self.compute.apply(&mut config.compute);
+        self.output.apply(&mut config.output)?;
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Local trust CSV (truster,trustee[,level]).
+    pub localtrust: PathBuf,
+    /// Write JSON instead of key=value lines.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphModel {
+    ErdosRenyi,
+    BarabasiAlbert,
+    StochasticBlock,
+}
+
+impl FromStr for GraphModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "er" => Ok(GraphModel::ErdosRenyi),
+            "ba" => Ok(GraphModel::BarabasiAlbert),
+            "sbm" => Ok(GraphModel::StochasticBlock),
+            _ => Err(format!("Unknown graph model: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    /// Honest graph model: er (Erdős–Rényi), ba (Barabási–Albert) or sbm (stochastic blocks).
+    #[arg(long, default_value = "ba")]
+    pub model: GraphModel,
+    /// Number of honest peers.
+    #[arg(long)]
+    pub peers: usize,
+    /// Edge probability for er.
+    #[arg(long, default_value_t = 0.01)]
+    pub p: f64,
+    /// Peers each new peer trusts for ba.
+    #[arg(long, default_value_t = 3)]
+    pub m: usize,
+    /// Number of equally sized blocks for sbm.
+    #[arg(long, default_value_t = 4)]
+    pub blocks: usize,
+    /// Edge probability within a block for sbm.
+    #[arg(long, default_value_t = 0.05)]
+    pub p_in: f64,
+    /// Edge probability between blocks for sbm.
+    #[arg(long, default_value_t = 0.001)]
+    pub p_out: f64,
+    /// Add a sybil region of this many peers.
+    #[arg(long, default_value_t = 0)]
+    pub sybils: usize,
+    /// Edge probability within the sybil region.
+    #[arg(long, default_value_t = 0.05)]
+    pub sybil_p: f64,
+    /// Add a collusion ring of this many peers.
+    #[arg(long, default_value_t = 0)]
+    pub ring: usize,
+    /// Edges from honest peers into each attack structure.
+    #[arg(long, default_value_t = 10)]
+    pub attack_edges: usize,
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Also write a pretrust CSV trusting the first --pretrusted honest peers.
+    #[arg(long)]
+    pub pretrust: Option<PathBuf>,
+    #[arg(long, default_value_t = 10)]
+    pub pretrusted: usize,
+    /// Local trust CSV destination; stdout by default.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    pub input: PathBuf,
+    /// Input format, guessed from the file extension if omitted.
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+    /// Output format, guessed from --output if omitted.
+    #[arg(long)]
+    pub to: Option<OutputFormat>,
+    #[arg(long)]
+    pub no_header: bool,
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    /// Format of both score files, guessed from the file extension if omitted.
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+    /// Output format: csv, tsv, json or jsonl.
+    #[arg(long, default_value = "csv")]
+    pub format: OutputFormat,
+    /// Only output the N largest changes.
+    #[arg(long)]
+    pub top: Option<usize>,
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExplainArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+    pub peer: String,
+    #[command(flatten)]
+    pub compute: ComputeOptionArgs,
+    /// Only list the N largest contributions.
+    #[arg(long)]
+    pub top: Option<usize>,
+    /// Also list the N highest weight trust paths from pretrusted peers.
+    #[arg(long, default_value_t = 0)]
+    pub paths: usize,
+    /// Maximum number of edges in a trust path.
+    #[arg(long, default_value_t = 4)]
+    pub max_depth: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct IngestArgs {
+    /// Events (from,to,type[,value]), e.g. alice,bob,follow or alice,bob,transfer,25.
+    pub events: PathBuf,
+    /// Events format, guessed from the file extension (csv if unknown).
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+    /// Weighting policy (.toml or .json) with weights by event type and aggregation.
+    #[arg(long)]
+    pub policy: Option<PathBuf>,
+    /// Weight of an event type, as type=weight; repeatable, overrides the policy.
+    #[arg(long = "weight", value_parser = parse_weight)]
+    pub weights: Vec<(String, f64)>,
+    /// How events between two peers add up: sum, log_sum or cap:<level>.
+    #[arg(long)]
+    pub aggregation: Option<Aggregation>,
+    /// Local trust CSV destination; stdout by default.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+fn parse_weight(s: &str) -> Result<(String, f64), String> {
+    let (kind, weight) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected type=weight: {}", s))?;
+    let weight = weight
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid weight: {}", weight))?;
+    Ok((kind.to_ascii_lowercase(), weight))
+}
+
+#[derive(Debug, Args)]
+pub struct CommunitiesArgs {
+    /// Local trust CSV (truster,trustee[,level]).
+    pub localtrust: PathBuf,
+    /// Pretrust CSV; if given, members are listed with scores, normalized to sum
+    /// to one within each community.
+    #[arg(long)]
+    pub pretrust: Option<PathBuf>,
+    /// Compute scores on each community alone instead of normalizing global scores.
+    #[arg(long, requires = "pretrust")]
+    pub within: bool,
+    #[command(flatten)]
+    pub compute: ComputeOptionArgs,
+    /// Only show the community of this peer.
+    #[arg(long)]
+    pub peer: Option<String>,
+    /// Only list the top N members of each community.
+    #[arg(long)]
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct CollusionArgs {
+    /// Local trust CSV (truster,trustee[,level]).
+    pub localtrust: PathBuf,
+    /// Pretrust CSV; if given, scores are computed to find groups holding too much trust.
+    #[arg(long)]
+    pub pretrust: Option<PathBuf>,
+    #[command(flatten)]
+    pub compute: ComputeOptionArgs,
+    /// Largest group to consider.
+    #[arg(long, default_value_t = 10)]
+    pub max_group_size: usize,
+    /// Minimum share of a group's incoming trust that comes from its members.
+    #[arg(long, default_value_t = 0.5)]
+    pub min_insularity: f64,
+    /// Only list the N most suspicious groups.
+    #[arg(long)]
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackKind {
+    Sybil,
+    Ring,
+    Washing,
+}
+
+impl FromStr for AttackKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sybil" => Ok(AttackKind::Sybil),
+            "ring" => Ok(AttackKind::Ring),
+            "washing" => Ok(AttackKind::Washing),
+            _ => Err(format!("Unknown attack: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+    /// Attack: sybil (a sybil region), ring (a collusion ring) or washing
+    /// (a compromised honest peer trusting attackers).
+    #[arg(long, default_value = "sybil")]
+    pub attack: AttackKind,
+    /// Number of attacker peers.
+    #[arg(long, default_value_t = 100)]
+    pub size: usize,
+    /// Edge probability within a sybil region.
+    #[arg(long, default_value_t = 0.1)]
+    pub density: f64,
+    /// Edges from random scored honest peers to sybils or the ring.
+    #[arg(long, default_value_t = 10)]
+    pub attack_edges: usize,
+    /// Honest peer compromised by washing; the top scored one by default.
+    #[arg(long)]
+    pub compromised: Option<String>,
+    /// Seed for choosing victims and sybil edges.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Simulate once per alpha, e.g. --alphas 0.1,0.3,0.5, instead of with --alpha.
+    #[arg(long, value_delimiter = ',')]
+    pub alphas: Vec<f64>,
+    #[command(flatten)]
+    pub compute: ComputeOptionArgs,
+}
+
+impl SimulateArgs {
+    pub fn attack(&self) -> Attack {
+        match self.attack {
+            AttackKind::Sybil => Attack::Sybil {
+                size: self.size,
+                density: self.density,
+                attack_edges: self.attack_edges,
+            },
+            AttackKind::Ring => Attack::CollusionRing {
+                size: self.size,
+                attack_edges: self.attack_edges,
+            },
+            AttackKind::Washing => Attack::TrustWashing {
+                size: self.size,
+                compromised: self.compromised.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub bind: String,
+    /// Number of request handling threads.
+    #[arg(long, default_value_t = 4)]
+    pub threads: usize,
+    /// Number of threads running background compute jobs.
+    #[arg(long, default_value_t = 2)]
+    pub job_workers: usize,
+}
+
+const SUBCOMMANDS: [&str; 12] = [
+    "compute", "validate", "stats", "convert", "diff", "explain", "generate", "ingest",
+    "communities", "collusion", "simulate", "serve",
+];
+
+// normalize_args keeps the original `eigentrust <localtrust> <pretrust>` invocation
+// working by treating a leading positional argument as the compute subcommand.
+pub fn normalize_args(mut args: Vec<String>) -> Vec<String> {
+    if let Some(first) = args.get(1) {
+        if !first.starts_with('-') && !SUBCOMMANDS.contains(&first.as_str()) && first != "help" {
+            args.insert(1, "compute".to_string());
+        }
+    }
+    args
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn write_output(output: &Option<PathBuf>, content: &str) -> Result<(), String> {
+    match output {
+        Some(path) => fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+// read_input returns the file as CSV, converting it first if it is in another format.
+fn read_input(path: &Path, format: Option<OutputFormat>) -> Result<String, String> {
+    let content = read_file(path)?;
+    match format.unwrap_or(OutputFormat::Csv) {
+        OutputFormat::Csv => Ok(content),
+        format => write_records(&read_records(&content, format)?, OutputFormat::Csv, false),
+    }
+}
+
+fn resolve_format(explicit: Option<OutputFormat>, path: Option<&Path>) -> Result<OutputFormat, String> {
+    explicit
+        .or_else(|| path.and_then(format_from_path))
+        .ok_or_else(|| "Cannot guess the file format, pass it explicitly".to_string())
+}
+
+pub fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Compute(args) => {
+            let config = args.effective_config()?;
+            log::info!(
+                "Effective config: {}",
+                serde_json::to_string(&config).map_err(|e| e.to_string())?
+            );
+
+            let localtrust = config.localtrust.as_ref().ok_or("Missing local trust path")?;
+            let pretrust = config.pretrust.as_ref().ok_or("Missing pretrust path")?;
+            let localtrust_csv = read_input(localtrust, config.input_format)?;
+            let pretrust_csv = read_input(pretrust, config.input_format)?;
+
+            let (scores, timings) = calculate_from_csv_with_timings(
+                &localtrust_csv,
+                &pretrust_csv,
+                &config.compute,
+                None,
+            )?;
+
+            let metadata = json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "config": config,
+                "peers": scores.len(),
+                "timings": timings,
+            });
+            if let Some(path) = &config.metadata_path {
+                let content = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+                write_output(&Some(path.clone()), &content)?;
+            }
+
+            let output = format_scores_with_metadata(&scores, &config.output, Some(&metadata))?;
+            write_output(&config.output_path, &output)
+        }
+        Command::Validate(args) => {
+            let report = validate_csv(&read_file(&args.localtrust)?, &read_file(&args.pretrust)?);
+
+            for warning in &report.warnings {
+                println!("warning: {}", warning);
+            }
+            for error in &report.errors {
+                println!("error: {}", error);
+            }
+            println!(
+                "{} local trust records, {} pretrust records, {} peers, {} errors, {} warnings",
+                report.local_trust_records,
+                report.pretrust_records,
+                report.peers,
+                report.errors.len(),
+                report.warnings.len()
+            );
+
+            if report.is_valid() {
+                Ok(())
+            } else {
+                Err("Validation failed".to_string())
+            }
+        }
+        Command::Stats(args) => {
+            let localtrust_csv = read_file(&args.localtrust)?;
+            let (local_trust, _) = read_local_trust_from_csv(strip_headers(&localtrust_csv))?;
+            let stats = graph_stats(&local_trust);
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?);
+            } else {
+                let value = serde_json::to_value(&stats).map_err(|e| e.to_string())?;
+                for (key, value) in value.as_object().into_iter().flatten() {
+                    match value.as_object() {
+                        Some(fields) => {
+                            for (field, value) in fields {
+                                println!("{}.{}={}", key, field, value);
+                            }
+                        }
+                        None => println!("{}={}", key, value),
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Generate(args) => {
+            let mut graph = SyntheticGraph::new(args.seed);
+            let honest = match args.model {
+                GraphModel::ErdosRenyi => graph.erdos_renyi(args.peers, args.p),
+                GraphModel::BarabasiAlbert => graph.barabasi_albert(args.peers, args.m),
+                GraphModel::StochasticBlock => {
+                    let blocks = args.blocks.max(1);
+                    let sizes: Vec<usize> = (0..blocks)
+                        .map(|i| args.peers / blocks + usize::from(i < args.peers % blocks))
+                        .collect();
+                    graph.stochastic_block(&sizes, args.p_in, args.p_out);
+                    0..args.peers
+                }
+            };
+            if args.sybils > 0 {
+                graph.sybil_region(honest.clone(), args.sybils, args.sybil_p, args.attack_edges);
+            }
+            if args.ring > 0 {
+                graph.collusion_ring(honest.clone(), args.ring, args.attack_edges);
+            }
+            log::info!("Generated {} peers and {} edges", graph.peer_count(), graph.edge_count());
+
+            if let Some(path) = &args.pretrust {
+                let pretrusted = honest.start..honest.end.min(honest.start + args.pretrusted);
+                write_output(&Some(path.clone()), &graph.pretrust_csv(pretrusted))?;
+            }
+            write_output(&args.output, &graph.to_csv())
+        }
+        Command::Ingest(args) => {
+            let mut policy = match &args.policy {
+                Some(path) => load_event_policy(path)?,
+                None => EventPolicy::default(),
+            };
+            policy.weights.extend(args.weights.iter().cloned());
+            if let Some(aggregation) = args.aggregation {
+                policy.aggregation = aggregation;
+            }
+
+            let from = args.from.or_else(|| format_from_path(&args.events));
+            let events = parse_events_csv(&read_input(&args.events, from)?)?;
+            let local_trust = derive_local_trust(&events, &policy)?;
+            log::info!("Derived {} local trust edges from {} events", local_trust.len(), events.len());
+            write_output(&args.output, &local_trust_to_csv(&local_trust))
+        }
+        Command::Convert(args) => {
+            let from = resolve_format(args.from, Some(&args.input))?;
+            let to = resolve_format(args.to, args.output.as_deref())?;
+
+            let records = read_records(&read_file(&args.input)?, from)?;
+            write_output(&args.output, &write_records(&records, to, !args.no_header)?)
+        }
+        Command::Diff(args) => {
+            let from_a = resolve_format(args.from, Some(&args.a))?;
+            let from_b = resolve_format(args.from, Some(&args.b))?;
+
+            let a = diff::read_scores(&read_records(&read_file(&args.a)?, from_a)?)?;
+            let b = diff::read_scores(&read_records(&read_file(&args.b)?, from_b)?)?;
+
+            let (mut rows, summary) = diff::diff_scores(&a, &b);
+            rows.truncate(args.top.unwrap_or(rows.len()));
+
+            log::info!(
+                "peers_a={} peers_b={} only_in_a={} only_in_b={} changed={} l1_distance={} max_abs_delta={}",
+                summary.peers_a,
+                summary.peers_b,
+                summary.only_in_a,
+                summary.only_in_b,
+                summary.changed,
+                summary.l1_distance,
+                summary.max_abs_delta
+            );
+
+            let records = diff::diff_to_records(&rows);
+            write_output(&args.output, &write_records(&records, args.format, true)?)
+        }
+        Command::Explain(args) => {
+            let mut options = ComputeOptions::default();
+            args.compute.apply(&mut options);
+
+            let mut engine = TrustEngine::from_csv(&read_file(&args.input.localtrust)?)?;
+            engine.compute(&read_file(&args.input.pretrust)?, &options)?;
+            let mut explanation = engine.explain(&args.peer)?;
+            explanation.contributions.truncate(args.top.unwrap_or(usize::MAX));
+
+            let mut output = serde_json::to_value(&explanation).map_err(|e| e.to_string())?;
+            if args.paths > 0 {
+                let paths = engine.trust_paths(&args.peer, args.paths, args.max_depth)?;
+                output["paths"] = serde_json::to_value(&paths).map_err(|e| e.to_string())?;
+            }
+            println!("{}", serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::Communities(args) => {
+            let mut options = ComputeOptions::default();
+            args.compute.apply(&mut options);
+            let localtrust_csv = read_file(&args.localtrust)?;
+            let (local_trust, timestamps, peers) =
+                read_timestamped_local_trust_from_csv(strip_headers(&localtrust_csv))?;
+            let only = match &args.peer {
+                Some(peer) => Some(*peers.map.get(peer).ok_or_else(|| format!("Invalid peer: {}", peer))?),
+                None => None,
+            };
+            let names: Vec<String> = (0..peers.get_max_value())
+                .map(|i| peers.map_reversed[&i].clone())
+                .collect();
+            let mut engine = TrustEngine::with_timestamped_graph(local_trust, timestamps, peers);
+            let communities = engine.communities();
+            let top = args.top.unwrap_or(usize::MAX);
+            // map_or rather than is_none_or, which needs Rust 1.82.
+            #[allow(clippy::unnecessary_map_or)]
+            let shown = |community: usize| only.map_or(true, |peer| communities.community[peer] == community);
+
+            let listed = match &args.pretrust {
+                Some(pretrust) => {
+                    engine.compute(&read_file(pretrust)?, &options)?;
+                    let mut scores = if args.within {
+                        engine.compute_within_communities(&communities, &options)?
+                    } else {
+                        engine.community_scores(&communities)?
+                    };
+                    scores.retain(|community| shown(community.community));
+                    for community in &mut scores {
+                        community.scores.truncate(top);
+                    }
+                    serde_json::to_value(&scores).map_err(|e| e.to_string())?
+                }
+                None => communities
+                    .members()
+                    .iter()
+                    .enumerate()
+                    .filter(|(community, _)| shown(*community))
+                    .map(|(community, members)| {
+                        let names: Vec<&String> =
+                            members.iter().take(top).map(|&i| &names[i]).collect();
+                        json!({"community": community, "size": members.len(), "peers": names})
+                    })
+                    .collect(),
+            };
+
+            let output = json!({
+                "count": communities.count,
+                "modularity": communities.modularity,
+                "communities": listed,
+            });
+            println!("{}", serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::Collusion(args) => {
+            let mut options = ComputeOptions::default();
+            args.compute.apply(&mut options);
+            let mut engine = TrustEngine::from_csv(&read_file(&args.localtrust)?)?;
+            if let Some(pretrust) = &args.pretrust {
+                engine.compute(&read_file(pretrust)?, &options)?;
+            }
+
+            let options = CollusionOptions {
+                max_group_size: args.max_group_size,
+                min_insularity: args.min_insularity,
+                ..CollusionOptions::default()
+            };
+            let mut groups = engine.detect_collusion(&options);
+            groups.truncate(args.top.unwrap_or(usize::MAX));
+            println!("{}", serde_json::to_string_pretty(&groups).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::Simulate(args) => {
+            let mut options = ComputeOptions::default();
+            args.compute.apply(&mut options);
+            let alphas = if args.alphas.is_empty() {
+                vec![options.alpha]
+            } else {
+                args.alphas.clone()
+            };
+
+            let localtrust_csv = read_file(&args.input.localtrust)?;
+            let pretrust_csv = read_file(&args.input.pretrust)?;
+            let attack = args.attack();
+            let mut reports = vec![];
+            for alpha in alphas {
+                let options = ComputeOptions {
+                    alpha,
+                    ..options.clone()
+                };
+                reports.push(simulate_attack(
+                    &localtrust_csv,
+                    &pretrust_csv,
+                    &attack,
+                    &options,
+                    args.seed,
+                )?);
+            }
+            println!("{}", serde_json::to_string_pretty(&reports).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::Serve(args) => serve(&args.bind, args.threads, args.job_workers),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cli() {
+        let args = normalize_args(
+            [
+                "eigentrust", "lt.csv", "pt.csv", "--alpha", "0.2", "--distrust", "discount",
+                "--algorithm", "ppr", "--prune-unreachable",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        );
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        match cli.command {
+            Command::Compute(args) => {
+                let config = args.effective_config().unwrap();
+                assert_eq!(config.localtrust, Some(PathBuf::from("lt.csv")));
+                assert_eq!(config.compute.alpha, 0.2);
+                assert_eq!(config.compute.distrust, DistrustPolicy::Discount);
+                assert_eq!(config.compute.algorithm, Algorithm::PersonalizedPageRank);
+                assert!(config.compute.prune_unreachable);
+                assert_eq!(config.output, OutputOptions::default());
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        // Flags override the config either way.
+        let dir = std::env::temp_dir().join(format!("eigentrust-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("run.toml");
+        fs::write(
+            &config_path,
+            "[compute]\nprune_unreachable = true\ninclude_unreachable = true\n[output]\nheader = true\n",
+        )
+        .unwrap();
+        let config = config_path.to_str().unwrap();
+        let cli = Cli::try_parse_from([
+            "eigentrust", "compute", "lt.csv", "pt.csv", "--config", config,
+            "--no-prune-unreachable", "--no-header",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Compute(args) => {
+                let config = args.effective_config().unwrap();
+                assert!(!config.compute.prune_unreachable);
+                assert!(config.compute.include_unreachable);
+                assert!(!config.output.header);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        let cli = Cli::try_parse_from(["eigentrust", "stats", "lt.csv", "--log-level", "warn"]).unwrap();
+        assert_eq!(cli.log_level, Some(LevelFilter::Warn));
+        assert!(matches!(cli.command, Command::Stats(_)));
+
+        let cli = Cli::try_parse_from([
+            "eigentrust", "simulate", "lt.csv", "pt.csv", "--attack", "ring", "--size", "5",
+            "--alphas", "0.1,0.3",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Simulate(args) => {
+                assert_eq!(args.alphas, vec![0.1, 0.3]);
+                assert_eq!(
+                    args.attack(),
+                    Attack::CollusionRing {
+                        size: 5,
+                        attack_edges: 10
+                    }
+                );
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+}
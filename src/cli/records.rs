@@ -0,0 +1,227 @@
+use crate::basic::output::OutputFormat;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+// Records is a loosely typed table used to move local trust, pretrust and
+// score files between the supported formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Records {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Records {
+    pub fn column(&self, names: &[&str]) -> Option<usize> {
+        self.header
+            .iter()
+            .position(|h| names.iter().any(|n| h.eq_ignore_ascii_case(n)))
+    }
+}
+
+pub fn format_from_path(path: &Path) -> Option<OutputFormat> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| ext.parse().ok())
+}
+
+// Header used when a delimited file has none, following go-eigentrust's
+// i,j,v (local trust) and i,v (pretrust) naming.
+fn default_header(len: usize) -> Vec<String> {
+    match len {
+        1 => vec!["i".to_string()],
+        2 => vec!["i".to_string(), "v".to_string()],
+        3 => vec!["i".to_string(), "j".to_string(), "v".to_string()],
+        _ => (1..=len).map(|i| format!("col{}", i)).collect(),
+    }
+}
+
+// A header is detected with the same rule as strip_headers: the last field
+// of the first line is not a number.
+fn read_delimited(text: &str, delimiter: char) -> Records {
+    let first = text.lines().next().unwrap_or("");
+    let has_header = first
+        .rsplit(delimiter)
+        .next()
+        .map(|f| !f.is_empty() && f.parse::<f64>().is_err())
+        .unwrap_or(false);
+
+    let mut lines = text.lines();
+    let header = if has_header {
+        lines
+            .next()
+            .map(|l| l.split(delimiter).map(|f| f.to_string()).collect())
+    } else {
+        None
+    };
+
+    let rows: Vec<Vec<String>> = lines
+        .filter(|l| !l.is_empty())
+        .map(|l| l.split(delimiter).map(|f| f.to_string()).collect())
+        .collect();
+
+    let header = header.unwrap_or_else(|| default_header(rows.first().map_or(0, |r| r.len())));
+    Records { header, rows }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn read_objects(objects: &[Value]) -> Result<Records, String> {
+    let mut header: Vec<String> = vec![];
+    for object in objects {
+        let object = object
+            .as_object()
+            .ok_or_else(|| "Expected a JSON object per record".to_string())?;
+        for key in object.keys() {
+            if !header.contains(key) {
+                header.push(key.clone());
+            }
+        }
+    }
+
+    let rows = objects
+        .iter()
+        .map(|object| {
+            header
+                .iter()
+                .map(|key| object.get(key).map(value_to_string).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+
+    Ok(Records { header, rows })
+}
+
+pub fn read_records(text: &str, format: OutputFormat) -> Result<Records, String> {
+    match format {
+        OutputFormat::Csv => Ok(read_delimited(text, ',')),
+        OutputFormat::Tsv => Ok(read_delimited(text, '\t')),
+        OutputFormat::Json => {
+            let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+            match value {
+                Value::Array(objects) => read_objects(&objects),
+                Value::Object(mut object) => match object.remove("scores") {
+                    Some(Value::Array(objects)) => read_objects(&objects),
+                    _ => Err("Expected a JSON array or an object with a \"scores\" array".to_string()),
+                },
+                _ => Err("Expected a JSON array or an object with a \"scores\" array".to_string()),
+            }
+        }
+        OutputFormat::Jsonl => {
+            let objects = text
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .enumerate()
+                .map(|(i, l)| {
+                    serde_json::from_str(l).map_err(|e| format!("line {}: {}", i + 1, e))
+                })
+                .collect::<Result<Vec<Value>, String>>()?;
+            read_objects(&objects)
+        }
+    }
+}
+
+// Peer names stay strings even when they look numeric; everything else
+// that parses as a number is written as a JSON number.
+fn field_to_value(name: &str, field: &str) -> Value {
+    let is_peer = ["peer", "i", "j"].iter().any(|n| name.eq_ignore_ascii_case(n));
+    if is_peer {
+        return Value::from(field);
+    }
+    if field.is_empty() {
+        return Value::Null;
+    }
+    match field.parse::<f64>() {
+        Ok(number) if number.is_finite() => Value::from(number),
+        _ => Value::from(field),
+    }
+}
+
+fn row_to_object(header: &[String], row: &[String]) -> Value {
+    let mut object = Map::new();
+    for (name, field) in header.iter().zip(row) {
+        object.insert(name.clone(), field_to_value(name, field));
+    }
+    Value::Object(object)
+}
+
+pub fn write_records(records: &Records, format: OutputFormat, header: bool) -> Result<String, String> {
+    let delimited = |delimiter: &str| {
+        let mut out = String::new();
+        if header {
+            out.push_str(&records.header.join(delimiter));
+            out.push('\n');
+        }
+        for row in &records.rows {
+            out.push_str(&row.join(delimiter));
+            out.push('\n');
+        }
+        out
+    };
+
+    match format {
+        OutputFormat::Csv => Ok(delimited(",")),
+        OutputFormat::Tsv => Ok(delimited("\t")),
+        OutputFormat::Json => {
+            let objects: Vec<Value> = records
+                .rows
+                .iter()
+                .map(|row| row_to_object(&records.header, row))
+                .collect();
+            serde_json::to_string(&objects).map_err(|e| e.to_string())
+        }
+        OutputFormat::Jsonl => {
+            let mut out = String::new();
+            for row in &records.rows {
+                out.push_str(
+                    &serde_json::to_string(&row_to_object(&records.header, row))
+                        .map_err(|e| e.to_string())?,
+                );
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_records() {
+        let records = read_records("alice,bob,1\nbob,carol,2", OutputFormat::Csv).unwrap();
+        assert_eq!(records.header, vec!["i", "j", "v"]);
+        assert_eq!(records.rows.len(), 2);
+
+        let records = read_records("peer\tscore\n2\t0.5\n", OutputFormat::Tsv).unwrap();
+        assert_eq!(records.header, vec!["peer", "score"]);
+        assert_eq!(records.rows, vec![vec!["2", "0.5"]]);
+
+        let records = read_records(
+            r#"{"scores":[{"peer":"alice","score":0.5},{"peer":"bob","score":0.25,"rank":2}]}"#,
+            OutputFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(records.header, vec!["peer", "score", "rank"]);
+        assert_eq!(records.rows[0], vec!["alice", "0.5", ""]);
+        assert_eq!(records.column(&["score", "v"]), Some(1));
+    }
+
+    #[test]
+    fn test_convert_round_trip() {
+        let csv = "i,v\nalice,1\n2,0.5\n";
+        let records = read_records(csv, OutputFormat::Csv).unwrap();
+
+        let jsonl = write_records(&records, OutputFormat::Jsonl, true).unwrap();
+        assert_eq!(jsonl, "{\"i\":\"alice\",\"v\":1.0}\n{\"i\":\"2\",\"v\":0.5}\n");
+
+        let back = read_records(&jsonl, OutputFormat::Jsonl).unwrap();
+        assert_eq!(write_records(&back, OutputFormat::Csv, true).unwrap(), csv.replace(",1\n", ",1.0\n"));
+    }
+}
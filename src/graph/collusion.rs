@@ -0,0 +1,426 @@
+use crate::basic::util::PeersMap;
+use crate::graph::components::{component_sizes, strongly_connected_components};
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+// Clique search stops after this many maximal cliques, so that dense graphs
+// cannot run away.
+const MAX_CLIQUES: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollusionOptions {
+    // Largest group considered; bigger structures are communities, not rings.
+    pub max_group_size: usize,
+    // Smallest reciprocal clique reported.
+    pub min_clique_size: usize,
+    // Share of a peer's incoming trust that its top trusters must account for
+    // to be a concentrated trust candidate.
+    pub concentration: f64,
+    // Groups are only reported if at least this share of the trust they
+    // receive comes from inside the group.
+    pub min_insularity: f64,
+    // Groups holding this many times their fair share (size / peers) of
+    // global trust are flagged as disproportionate.
+    pub trust_ratio: f64,
+}
+
+impl Default for CollusionOptions {
+    fn default() -> Self {
+        CollusionOptions {
+            max_group_size: 10,
+            min_clique_size: 3,
+            concentration: 0.9,
+            min_insularity: 0.5,
+            trust_ratio: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    // Every member trusts every other and is trusted back.
+    ReciprocalClique,
+    // A member's incoming trust comes almost entirely from the others.
+    ConcentratedTrust,
+    // A strongly connected component of its own.
+    SmallComponent,
+    // Holds far more global trust than its size suggests.
+    DisproportionateTrust,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuspiciousGroup {
+    pub peers: Vec<String>,
+    pub reasons: Vec<Reason>,
+    // insularity * size / (size + external_trusters), which ranks the groups:
+    // trust that mostly circulates inside, fed by few outsiders.
+    pub suspicion: f64,
+    // Share of ordered member pairs with positive trust.
+    pub density: f64,
+    // Share of edges between members that are trusted back.
+    pub reciprocity: f64,
+    // Share of the canonicalized trust members receive that comes from members.
+    pub insularity: f64,
+    // Peers outside the group that trust a member.
+    pub external_trusters: usize,
+    // Sum of the members' global trust, with scores only.
+    pub trust: Option<f64>,
+    // trust / (size / peers), with scores only.
+    pub trust_ratio: Option<f64>,
+}
+
+// Positive local trust, rows canonicalized, without self-trust.
+struct TrustGraph {
+    out: Vec<Vec<(usize, f64)>>,
+    incoming: Vec<Vec<(usize, f64)>>,
+}
+
+impl TrustGraph {
+    fn new(local_trust: &CSRMatrix) -> Self {
+        let n = local_trust.cs_matrix.major_dim;
+        let mut out = vec![vec![]; n];
+        let mut incoming = vec![vec![]; n];
+        for (i, row) in local_trust.cs_matrix.entries.iter().enumerate() {
+            let positive = || {
+                row.iter()
+                    .filter(|e| e.value > 0.0 && e.index != i && e.index < n)
+            };
+            let sum: f64 = positive().map(|e| e.value).sum();
+            for e in positive() {
+                out[i].push((e.index, e.value / sum));
+                incoming[e.index].push((i, e.value / sum));
+            }
+        }
+        TrustGraph { out, incoming }
+    }
+
+    fn trusts(&self, i: usize, j: usize) -> bool {
+        self.out[i]
+            .binary_search_by_key(&j, |&(index, _)| index)
+            .is_ok()
+    }
+
+    // reciprocal returns each peer's mutually trusting peers, sorted.
+    fn reciprocal(&self) -> Vec<Vec<usize>> {
+        (0..self.out.len())
+            .map(|i| {
+                self.out[i]
+                    .iter()
+                    .map(|&(j, _)| j)
+                    .filter(|&j| self.trusts(j, i))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+    a.iter()
+        .cloned()
+        .filter(|v| b.binary_search(v).is_ok())
+        .collect()
+}
+
+// bron_kerbosch collects maximal cliques of the reciprocal graph with pivoting.
+// Recursion depth is bounded by the clique size.
+fn bron_kerbosch(
+    neighbours: &[Vec<usize>],
+    clique: &mut Vec<usize>,
+    mut candidates: Vec<usize>,
+    mut excluded: Vec<usize>,
+    cliques: &mut Vec<Vec<usize>>,
+) {
+    if cliques.len() >= MAX_CLIQUES {
+        return;
+    }
+    if candidates.is_empty() {
+        if excluded.is_empty() {
+            cliques.push(clique.clone());
+        }
+        return;
+    }
+
+    let pivot = candidates
+        .iter()
+        .chain(&excluded)
+        .max_by_key(|&&u| intersect(&candidates, &neighbours[u]).len())
+        .cloned()
+        .unwrap();
+    let expand: Vec<usize> = candidates
+        .iter()
+        .cloned()
+        .filter(|v| neighbours[pivot].binary_search(v).is_err())
+        .collect();
+
+    for v in expand {
+        clique.push(v);
+        bron_kerbosch(
+            neighbours,
+            clique,
+            intersect(&candidates, &neighbours[v]),
+            intersect(&excluded, &neighbours[v]),
+            cliques,
+        );
+        clique.pop();
+        candidates.retain(|&u| u != v);
+        let position = excluded.partition_point(|&u| u < v);
+        excluded.insert(position, v);
+    }
+}
+
+fn reciprocal_cliques(graph: &TrustGraph, min_size: usize, max_size: usize) -> Vec<Vec<usize>> {
+    let neighbours = graph.reciprocal();
+    let mut cliques = vec![];
+    for v in 0..neighbours.len() {
+        if neighbours[v].len() + 1 < min_size {
+            continue;
+        }
+        // Each clique is found from its lowest peer only.
+        let (excluded, candidates): (Vec<usize>, Vec<usize>) =
+            neighbours[v].iter().partition(|&&u| u < v);
+        bron_kerbosch(
+            &neighbours,
+            &mut vec![v],
+            candidates,
+            excluded,
+            &mut cliques,
+        );
+    }
+    if cliques.len() >= MAX_CLIQUES {
+        log::warn!("Stopped clique search after {} cliques", MAX_CLIQUES);
+    }
+    cliques.retain(|clique| (min_size..=max_size).contains(&clique.len()));
+    for clique in &mut cliques {
+        clique.sort_unstable();
+    }
+    cliques
+}
+
+// concentrated_groups returns, for each peer, the peer together with the
+// fewest trusters that give it the given share of its incoming trust.
+fn concentrated_groups(graph: &TrustGraph, concentration: f64, max_size: usize) -> Vec<Vec<usize>> {
+    let mut groups = vec![];
+    for (j, trusters) in graph.incoming.iter().enumerate() {
+        let total: f64 = trusters.iter().map(|&(_, c)| c).sum();
+        if total <= 0.0 {
+            continue;
+        }
+        let mut trusters = trusters.clone();
+        trusters.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut group = vec![j];
+        let mut covered = 0.0;
+        for (i, c) in trusters {
+            if covered >= concentration * total || group.len() >= max_size {
+                break;
+            }
+            group.push(i);
+            covered += c;
+        }
+        if covered >= concentration * total {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+fn describe(
+    graph: &TrustGraph,
+    members: &BTreeSet<usize>,
+    reasons: Vec<Reason>,
+    scores: Option<&[f64]>,
+    peers: &PeersMap,
+) -> SuspiciousGroup {
+    let size = members.len();
+    let mut edges = 0;
+    let mut reciprocated = 0;
+    let mut inside = 0.0;
+    let mut total = 0.0;
+    let mut external = HashSet::new();
+
+    for &j in members {
+        for &(i, c) in &graph.incoming[j] {
+            total += c;
+            if members.contains(&i) {
+                inside += c;
+                edges += 1;
+                if graph.trusts(j, i) {
+                    reciprocated += 1;
+                }
+            } else {
+                external.insert(i);
+            }
+        }
+    }
+
+    let pairs = size * (size - 1);
+    let density = if pairs > 0 {
+        edges as f64 / pairs as f64
+    } else {
+        0.0
+    };
+    let insularity = if total > 0.0 { inside / total } else { 0.0 };
+    let trust = scores.map(|scores| members.iter().map(|&i| scores[i]).sum::<f64>());
+
+    SuspiciousGroup {
+        peers: members
+            .iter()
+            .map(|i| peers.map_reversed.get(i).cloned().unwrap_or_default())
+            .collect(),
+        reasons,
+        suspicion: insularity * size as f64 / (size + external.len()) as f64,
+        density,
+        reciprocity: if edges > 0 {
+            reciprocated as f64 / edges as f64
+        } else {
+            0.0
+        },
+        insularity,
+        external_trusters: external.len(),
+        trust,
+        trust_ratio: scores
+            .zip(trust)
+            .map(|(scores, trust)| trust * scores.len() as f64 / size as f64),
+    }
+}
+
+// detect_collusion flags small groups of peers in parsed local trust that
+// look like they trade trust among themselves: reciprocal cliques, peers
+// whose incoming trust is concentrated in a few trusters, and small strongly
+// connected components. Only groups receiving at least min_insularity of
+// their trust from inside are kept, most suspicious first. With the scores
+// of a compute, groups holding a disproportionate share of trust are marked.
+pub fn detect_collusion(
+    local_trust: &CSRMatrix,
+    scores: Option<&Vector>,
+    peers: &PeersMap,
+    options: &CollusionOptions,
+) -> Vec<SuspiciousGroup> {
+    let n = local_trust.cs_matrix.major_dim;
+    let graph = TrustGraph::new(local_trust);
+    let max_size = options.max_group_size;
+
+    let mut candidates: HashMap<BTreeSet<usize>, BTreeSet<Reason>> = HashMap::new();
+    let mut add = |group: Vec<usize>, reason: Reason| {
+        if group.len() >= 2 {
+            candidates
+                .entry(group.into_iter().collect())
+                .or_default()
+                .insert(reason);
+        }
+    };
+
+    for clique in reciprocal_cliques(&graph, options.min_clique_size.max(2), max_size) {
+        add(clique, Reason::ReciprocalClique);
+    }
+    for group in concentrated_groups(&graph, options.concentration, max_size) {
+        add(group, Reason::ConcentratedTrust);
+    }
+    let components = strongly_connected_components(local_trust);
+    let sizes = component_sizes(&components);
+    let mut members = vec![vec![]; sizes.len()];
+    for (i, &c) in components.iter().enumerate() {
+        if sizes[c] <= max_size {
+            members[c].push(i);
+        }
+    }
+    for group in members {
+        add(group, Reason::SmallComponent);
+    }
+
+    let dense_scores = scores.map(|scores| {
+        let mut dense = vec![0.0; n];
+        for e in scores.entries.iter().filter(|e| e.index < n) {
+            dense[e.index] = e.value;
+        }
+        dense
+    });
+
+    let mut groups: Vec<SuspiciousGroup> = candidates
+        .into_iter()
+        .map(|(members, reasons)| {
+            let mut group = describe(
+                &graph,
+                &members,
+                reasons.into_iter().collect(),
+                dense_scores.as_deref(),
+                peers,
+            );
+            if group
+                .trust_ratio
+                .is_some_and(|ratio| ratio >= options.trust_ratio)
+            {
+                group.reasons.push(Reason::DisproportionateTrust);
+            }
+            group
+        })
+        .filter(|group| group.insularity >= options.min_insularity)
+        .collect();
+
+    groups.sort_by(|a, b| {
+        b.suspicion
+            .total_cmp(&a.suspicion)
+            .then_with(|| b.trust.unwrap_or(0.0).total_cmp(&a.trust.unwrap_or(0.0)))
+            .then_with(|| a.peers.cmp(&b.peers))
+    });
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::engine::{ComputeOptions, TrustEngine};
+    use crate::graph::generate::SyntheticGraph;
+
+    #[test]
+    fn test_reciprocal_cliques() {
+        // 0, 1, 2, 3 all trust each other; 3 <-> 4; 4 -> 5 -> 4
+        let mut entries = vec![(3, 4, 1.0), (4, 3, 1.0), (4, 5, 1.0), (5, 4, 1.0)];
+        for i in 0..4 {
+            for j in 0..4 {
+                if i != j {
+                    entries.push((i, j, 1.0));
+                }
+            }
+        }
+        let graph = TrustGraph::new(&CSRMatrix::new(6, 6, entries));
+        assert_eq!(
+            reciprocal_cliques(&graph, 2, 10),
+            vec![vec![0, 1, 2, 3], vec![3, 4], vec![4, 5]]
+        );
+        assert_eq!(reciprocal_cliques(&graph, 3, 10), vec![vec![0, 1, 2, 3]]);
+        assert!(reciprocal_cliques(&graph, 3, 3).is_empty());
+    }
+
+    #[test]
+    fn test_detect_collusion() {
+        let mut graph = SyntheticGraph::new(11);
+        let honest = graph.barabasi_albert(500, 3);
+        let ring = graph.collusion_ring(honest.clone(), 4, 2);
+        let pretrust = graph.pretrust_csv(honest.start..honest.start + 5);
+
+        let mut engine = TrustEngine::from_csv(&graph.to_csv()).unwrap();
+        let options = ComputeOptions {
+            alpha: 0.1,
+            ..ComputeOptions::default()
+        };
+        engine.compute(&pretrust, &options).unwrap();
+        let groups = engine.detect_collusion(&CollusionOptions::default());
+
+        let top = &groups[0];
+        let ring: Vec<String> = ring.map(|i| format!("ring{}", i)).collect();
+        assert_eq!(top.peers, ring);
+        assert_eq!(top.reasons, vec![Reason::SmallComponent]);
+        assert!(top.insularity > 0.9);
+        assert_eq!(top.external_trusters, 2);
+        assert_eq!(top.density, 1.0 / 3.0);
+        assert_eq!(top.reciprocity, 0.0);
+        assert!(top.trust.is_some());
+        assert!(groups[1].suspicion < top.suspicion / 2.0);
+        assert!(groups.iter().all(|group| group.insularity >= 0.5));
+    }
+}
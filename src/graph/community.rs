@@ -0,0 +1,264 @@
+use crate::sparse::matrix::CSRMatrix;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Communities {
+    // Community of each peer. Communities are numbered by descending size,
+    // ties broken by their lowest peer.
+    pub community: Vec<usize>,
+    pub count: usize,
+    pub modularity: f64,
+}
+
+impl Communities {
+    // members returns the peers of each community, in index order.
+    pub fn members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![vec![]; self.count];
+        for (peer, &c) in self.community.iter().enumerate() {
+            members[c].push(peer);
+        }
+        members
+    }
+}
+
+// CommunityScores are the scores of one community's members, by descending
+// score, either global scores normalized to sum to one within the community
+// or scores computed on the community alone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommunityScores {
+    pub community: usize,
+    pub size: usize,
+    // Sum of the members' global scores.
+    pub trust: f64,
+    pub scores: Vec<(String, f64)>,
+}
+
+// Weighted undirected graph as adjacency lists; a self-loop appears once.
+type Graph = Vec<Vec<(usize, f64)>>;
+
+// symmetrize turns local trust into an undirected graph weighted by
+// c_ij + c_ji, where c is the positive local trust with rows canonicalized,
+// so that every peer has the same say whatever scale it rates on.
+fn symmetrize(local_trust: &CSRMatrix) -> Graph {
+    let n = local_trust.cs_matrix.major_dim;
+    let mut weights: Vec<HashMap<usize, f64>> = vec![HashMap::new(); n];
+    for (i, row) in local_trust.cs_matrix.entries.iter().enumerate() {
+        let positive = || row.iter().filter(|e| e.value > 0.0 && e.index != i && e.index < n);
+        let sum: f64 = positive().map(|e| e.value).sum();
+        for e in positive() {
+            *weights[i].entry(e.index).or_default() += e.value / sum;
+            *weights[e.index].entry(i).or_default() += e.value / sum;
+        }
+    }
+    weights
+        .into_iter()
+        .map(|row| {
+            let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+            row.sort_by_key(|&(j, _)| j);
+            row
+        })
+        .collect()
+}
+
+fn degrees(graph: &Graph) -> Vec<f64> {
+    graph
+        .iter()
+        .map(|row| row.iter().map(|&(_, w)| w).sum())
+        .collect()
+}
+
+// modularity_of is sum over communities of in_c / 2m - (tot_c / 2m)^2.
+fn modularity_of(graph: &Graph, community: &[usize]) -> f64 {
+    let k = degrees(graph);
+    let m2: f64 = k.iter().sum();
+    if m2 == 0.0 {
+        return 0.0;
+    }
+    let count = community.iter().map(|&c| c + 1).max().unwrap_or(0);
+    let mut inside = vec![0.0; count];
+    let mut total = vec![0.0; count];
+    for (i, row) in graph.iter().enumerate() {
+        total[community[i]] += k[i];
+        for &(j, w) in row {
+            if community[i] == community[j] {
+                inside[community[i]] += w;
+            }
+        }
+    }
+    (0..count)
+        .map(|c| inside[c] / m2 - (total[c] / m2).powi(2))
+        .sum()
+}
+
+// local_moves moves peers one at a time, in index order, to the neighbouring
+// community that increases modularity most, until no move helps. It returns
+// whether anything moved.
+fn local_moves(graph: &Graph, community: &mut [usize]) -> bool {
+    let k = degrees(graph);
+    let m2: f64 = k.iter().sum();
+    let mut total = vec![0.0; graph.len()];
+    for (i, &c) in community.iter().enumerate() {
+        total[c] += k[i];
+    }
+
+    let mut moved = false;
+    let mut links: HashMap<usize, f64> = HashMap::new();
+    loop {
+        let mut improved = false;
+        for i in 0..graph.len() {
+            let current = community[i];
+            links.clear();
+            for &(j, w) in &graph[i] {
+                if j != i {
+                    *links.entry(community[j]).or_default() += w;
+                }
+            }
+            total[current] -= k[i];
+
+            let gain = |c: usize, links: &HashMap<usize, f64>| {
+                links.get(&c).cloned().unwrap_or(0.0) - total[c] * k[i] / m2
+            };
+            let mut best = current;
+            let mut best_gain = gain(current, &links);
+            let mut candidates: Vec<usize> = links.keys().cloned().collect();
+            candidates.sort_unstable();
+            for c in candidates {
+                let g = gain(c, &links);
+                if g > best_gain + 1e-12 {
+                    best = c;
+                    best_gain = g;
+                }
+            }
+
+            total[best] += k[i];
+            if best != current {
+                community[i] = best;
+                improved = true;
+                moved = true;
+            }
+        }
+        if !improved {
+            return moved;
+        }
+    }
+}
+
+// aggregate merges each community into a single node.
+fn aggregate(graph: &Graph, community: &[usize], count: usize) -> Graph {
+    let mut weights: Vec<HashMap<usize, f64>> = vec![HashMap::new(); count];
+    for (i, row) in graph.iter().enumerate() {
+        for &(j, w) in row {
+            *weights[community[i]].entry(community[j]).or_default() += w;
+        }
+    }
+    weights
+        .into_iter()
+        .map(|row| {
+            let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+            row.sort_by_key(|&(j, _)| j);
+            row
+        })
+        .collect()
+}
+
+// renumber numbers communities from 0 in order of first appearance.
+fn renumber(community: &mut [usize]) -> usize {
+    let mut ids = HashMap::new();
+    for c in community.iter_mut() {
+        let next = ids.len();
+        *c = *ids.entry(*c).or_insert(next);
+    }
+    ids.len()
+}
+
+// louvain detects communities in local trust with the Louvain method on the
+// symmetrized, canonicalized positive trust. It is deterministic.
+pub fn louvain(local_trust: &CSRMatrix) -> Communities {
+    let graph = symmetrize(local_trust);
+    let n = graph.len();
+    let mut community: Vec<usize> = (0..n).collect();
+
+    let mut level = graph.clone();
+    loop {
+        let mut level_community: Vec<usize> = (0..level.len()).collect();
+        if level.is_empty() || !local_moves(&level, &mut level_community) {
+            break;
+        }
+        let count = renumber(&mut level_community);
+        for c in community.iter_mut() {
+            *c = level_community[*c];
+        }
+        level = aggregate(&level, &level_community, count);
+    }
+
+    // Largest first, then by lowest peer.
+    let count = renumber(&mut community);
+    let mut sizes = vec![0usize; count];
+    for &c in &community {
+        sizes[c] += 1;
+    }
+    let mut order: Vec<usize> = (0..count).collect();
+    order.sort_by_key(|&c| std::cmp::Reverse(sizes[c]));
+    let mut rank = vec![0; count];
+    for (position, &c) in order.iter().enumerate() {
+        rank[c] = position;
+    }
+    for c in community.iter_mut() {
+        *c = rank[*c];
+    }
+
+    Communities {
+        modularity: modularity_of(&graph, &community),
+        community,
+        count,
+    }
+}
+
+// modularity scores a division of local trust into communities on the same
+// symmetrized graph louvain uses.
+pub fn modularity(local_trust: &CSRMatrix, community: &[usize]) -> f64 {
+    modularity_of(&symmetrize(local_trust), community)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::generate::SyntheticGraph;
+
+    #[test]
+    fn test_louvain() {
+        // Two triangles joined by a single edge, and a lone peer.
+        let m = CSRMatrix::new(
+            7,
+            7,
+            vec![
+                (0, 1, 1.0),
+                (1, 2, 1.0),
+                (2, 0, 1.0),
+                (3, 4, 1.0),
+                (4, 5, 1.0),
+                (5, 3, 1.0),
+                (2, 3, 1.0),
+            ],
+        );
+        let communities = louvain(&m);
+        assert_eq!(communities.community, vec![0, 0, 0, 1, 1, 1, 2]);
+        assert_eq!(communities.count, 3);
+        assert!(communities.modularity > 0.3);
+        assert_eq!(
+            communities.modularity,
+            modularity(&m, &communities.community)
+        );
+        assert!(modularity(&m, &[0; 7]).abs() < 1e-12);
+        assert_eq!(communities.members()[1], vec![3, 4, 5]);
+
+        let mut graph = SyntheticGraph::new(9);
+        let blocks = graph.stochastic_block(&[60, 40, 30], 0.3, 0.005);
+        let communities = louvain(&graph.to_matrix().0);
+        assert_eq!(communities.count, 3);
+        for (c, block) in blocks.iter().enumerate() {
+            assert!(block.clone().all(|i| communities.community[i] == c));
+        }
+    }
+}
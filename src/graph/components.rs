@@ -0,0 +1,115 @@
+use crate::sparse::matrix::CSRMatrix;
+
+const UNVISITED: usize = usize::MAX;
+
+// strongly_connected_components returns the component of each peer along
+// positive local trust, numbered from 0 in reverse topological order: no
+// edge leads from a component to a higher numbered one.
+//
+// This is Tarjan's algorithm with an explicit call stack, so that long
+// trust chains cannot overflow the thread's stack.
+pub fn strongly_connected_components(local_trust: &CSRMatrix) -> Vec<usize> {
+    let rows = &local_trust.cs_matrix.entries;
+    let n = rows.len();
+
+    let mut index = vec![UNVISITED; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = vec![];
+    let mut component = vec![UNVISITED; n];
+    let mut next_index = 0;
+    let mut next_component = 0;
+    // (peer, position of the next edge to follow in its row)
+    let mut calls: Vec<(usize, usize)> = vec![];
+
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        calls.push((root, 0));
+
+        while let Some(&(v, position)) = calls.last() {
+            if let Some(e) = rows[v].get(position) {
+                calls.last_mut().unwrap().1 += 1;
+                let w = e.index;
+                if e.value <= 0.0 || w >= n {
+                    continue;
+                }
+                if index[w] == UNVISITED {
+                    index[w] = next_index;
+                    lowlink[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[v]);
+            }
+            if lowlink[v] == index[v] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component[w] = next_component;
+                    if w == v {
+                        break;
+                    }
+                }
+                next_component += 1;
+            }
+        }
+    }
+    component
+}
+
+// component_sizes counts the peers of each component.
+pub fn component_sizes(components: &[usize]) -> Vec<usize> {
+    let count = components.iter().map(|&c| c + 1).max().unwrap_or(0);
+    let mut sizes = vec![0; count];
+    for &c in components {
+        sizes[c] += 1;
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strongly_connected_components() {
+        // 0 <-> 1 -> 2 <-> 3 -> 4, 4 -| 0, 5 alone
+        let m = CSRMatrix::new(
+            6,
+            6,
+            vec![
+                (0, 1, 1.0),
+                (1, 0, 1.0),
+                (1, 2, 1.0),
+                (2, 3, 1.0),
+                (3, 2, 1.0),
+                (3, 4, 1.0),
+                (4, 0, -1.0),
+            ],
+        );
+        let components = strongly_connected_components(&m);
+        assert_eq!(components, vec![2, 2, 1, 1, 0, 3]);
+        assert_eq!(component_sizes(&components), vec![1, 2, 2, 1]);
+
+        // A chain far deeper than a recursive search could go.
+        let n = 1_000_000;
+        let m = CSRMatrix::new(n, n, (0..n).map(|i| (i, (i + 1) % n, 1.0)).collect());
+        let components = strongly_connected_components(&m);
+        assert_eq!(component_sizes(&components), vec![n]);
+    }
+}
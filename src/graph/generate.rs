@@ -0,0 +1,343 @@
+use crate::basic::util::PeersMap;
+use crate::sparse::matrix::CSRMatrix;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+// Rng is SplitMix64: small, fast and stable across releases, so that a seed
+// always gives the same graph.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // next_f64 is uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // below is uniform in [0, n); n must be positive.
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    // weight is a trust level, uniform in (0, 1].
+    fn weight(&mut self) -> f64 {
+        1.0 - self.next_f64()
+    }
+}
+
+// SyntheticGraph builds reproducible trust graphs out of groups of peers,
+// each added by a generator that returns the peer indices of the group:
+//
+//   let mut graph = SyntheticGraph::new(42);
+//   let honest = graph.barabasi_albert(10_000, 3);
+//   graph.sybil_region(honest, 1_000, 0.01, 10);
+//   let (local_trust, peers) = graph.to_matrix();
+//
+// Honest peers are named p<index>, sybils sybil<index> and colluders
+// ring<index>. Trust levels are uniform in (0, 1].
+#[derive(Debug, Clone)]
+pub struct SyntheticGraph {
+    rng: Rng,
+    names: Vec<String>,
+    edges: Vec<(usize, usize, f64)>,
+}
+
+impl SyntheticGraph {
+    pub fn new(seed: u64) -> Self {
+        SyntheticGraph {
+            rng: Rng::new(seed),
+            names: vec![],
+            edges: vec![],
+        }
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn edges(&self) -> &[(usize, usize, f64)] {
+        &self.edges
+    }
+
+    fn add_peers(&mut self, count: usize, prefix: &str) -> Range<usize> {
+        let start = self.names.len();
+        self.names
+            .extend((start..start + count).map(|i| format!("{}{}", prefix, i)));
+        start..start + count
+    }
+
+    // add_random_edges adds each edge from a truster to a trustee, other than
+    // self-trust, with probability p. Gaps between edges are drawn from the
+    // geometric distribution, so sparse graphs take time linear in their edges
+    // rather than in the square of their peers.
+    fn add_random_edges(&mut self, trusters: Range<usize>, trustees: Range<usize>, p: f64) {
+        // ln(1 - p), -inf for p = 1 so that nothing is skipped
+        let log_q = (1.0 - p.min(1.0)).ln();
+        if log_q >= 0.0 || log_q.is_nan() {
+            return;
+        }
+        for from in trusters {
+            let mut to = trustees.start;
+            loop {
+                let skip = ((1.0 - self.rng.next_f64()).ln() / log_q).floor();
+                if skip >= (trustees.end - to) as f64 {
+                    break;
+                }
+                to += skip as usize;
+                if from != to {
+                    let weight = self.rng.weight();
+                    self.edges.push((from, to, weight));
+                }
+                to += 1;
+            }
+        }
+    }
+
+    // add_attack_edges adds count distinct edges from random peers of one
+    // group to random peers of another, at most one per pair.
+    fn add_attack_edges(&mut self, from: Range<usize>, to: Range<usize>, count: usize) {
+        if from.is_empty() || to.is_empty() {
+            return;
+        }
+        let count = count.min(from.len() * to.len());
+        let mut added = HashSet::new();
+        while added.len() < count {
+            let edge = (
+                from.start + self.rng.below(from.len()),
+                to.start + self.rng.below(to.len()),
+            );
+            if edge.0 != edge.1 && added.insert(edge) {
+                let weight = self.rng.weight();
+                self.edges.push((edge.0, edge.1, weight));
+            }
+        }
+    }
+
+    // erdos_renyi adds n peers, each trusting each other with probability p.
+    pub fn erdos_renyi(&mut self, n: usize, p: f64) -> Range<usize> {
+        let peers = self.add_peers(n, "p");
+        self.add_random_edges(peers.clone(), peers.clone(), p);
+        peers
+    }
+
+    // barabasi_albert adds n peers by preferential attachment: the first
+    // m + 1 all trust each other, then each peer picks m distinct earlier
+    // peers in proportion to their degree. Trust goes both ways, with
+    // independent levels, so that it reaches newer peers too. Degrees follow
+    // a power law.
+    pub fn barabasi_albert(&mut self, n: usize, m: usize) -> Range<usize> {
+        let peers = self.add_peers(n, "p");
+        let m = m.max(1);
+        let seed_count = (m + 1).min(n);
+        // Every peer appears here once per edge it is part of.
+        let mut endpoints = vec![];
+
+        for from in peers.start..peers.start + seed_count {
+            for to in peers.start..peers.start + seed_count {
+                if from != to {
+                    let weight = self.rng.weight();
+                    self.edges.push((from, to, weight));
+                    endpoints.push(from);
+                }
+            }
+        }
+
+        for from in peers.start + seed_count..peers.end {
+            let mut targets = HashSet::new();
+            while targets.len() < m {
+                targets.insert(endpoints[self.rng.below(endpoints.len())]);
+            }
+            let mut targets: Vec<usize> = targets.into_iter().collect();
+            targets.sort_unstable();
+            for to in targets {
+                let (weight, back) = (self.rng.weight(), self.rng.weight());
+                self.edges.push((from, to, weight));
+                self.edges.push((to, from, back));
+                endpoints.extend([from, to]);
+            }
+        }
+        peers
+    }
+
+    // stochastic_block adds a block of peers per size. Peers trust others in
+    // their block with probability p_in and in other blocks with p_out.
+    pub fn stochastic_block(
+        &mut self,
+        sizes: &[usize],
+        p_in: f64,
+        p_out: f64,
+    ) -> Vec<Range<usize>> {
+        let blocks: Vec<Range<usize>> = sizes
+            .iter()
+            .map(|&size| self.add_peers(size, "p"))
+            .collect();
+        for from in &blocks {
+            for to in &blocks {
+                let p = if from == to { p_in } else { p_out };
+                self.add_random_edges(from.clone(), to.clone(), p);
+            }
+        }
+        blocks
+    }
+
+    // sybil_region adds size sybils that trust each other with probability
+    // p, and attack_edges edges through which honest peers trust sybils.
+    // Sybils also trust honest peers back, as cheaply as they like.
+    pub fn sybil_region(
+        &mut self,
+        honest: Range<usize>,
+        size: usize,
+        p: f64,
+        attack_edges: usize,
+    ) -> Range<usize> {
+        let sybils = self.add_peers(size, "sybil");
+        self.add_random_edges(sybils.clone(), sybils.clone(), p);
+        self.add_attack_edges(honest.clone(), sybils.clone(), attack_edges);
+        self.add_attack_edges(sybils.clone(), honest, attack_edges);
+        sybils
+    }
+
+    // collusion_ring adds size colluders that each fully trust the next one
+    // around a ring, and attack_edges edges from honest peers into the ring.
+    pub fn collusion_ring(
+        &mut self,
+        honest: Range<usize>,
+        size: usize,
+        attack_edges: usize,
+    ) -> Range<usize> {
+        let ring = self.add_peers(size, "ring");
+        if size > 1 {
+            for i in 0..size {
+                self.edges
+                    .push((ring.start + i, ring.start + (i + 1) % size, 1.0));
+            }
+        }
+        self.add_attack_edges(honest, ring.clone(), attack_edges);
+        ring
+    }
+
+    // to_matrix returns the graph as parsed local trust would be.
+    pub fn to_matrix(&self) -> (CSRMatrix, PeersMap) {
+        let mut peers = PeersMap::new();
+        for name in &self.names {
+            peers.insert_or_get(name.clone());
+        }
+        let n = self.names.len();
+        (CSRMatrix::new(n, n, self.edges.clone()), peers)
+    }
+
+    // to_csv returns the graph as local trust CSV, without a header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for &(from, to, level) in &self.edges {
+            let _ = writeln!(csv, "{},{},{}", self.names[from], self.names[to], level);
+        }
+        csv
+    }
+
+    // pretrust_csv pretrusts the given peers equally.
+    pub fn pretrust_csv(&self, peers: Range<usize>) -> String {
+        peers.map(|i| format!("{},1\n", self.names[i])).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::engine::{calculate_from_csv_with_options, ComputeOptions};
+    use crate::graph::stats::graph_stats;
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let values: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(values, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(Rng::new(8).next_u64(), values[0]);
+        assert!((0..1000).all(|_| a.below(10) < 10 && a.next_f64() < 1.0));
+    }
+
+    #[test]
+    fn test_generators() {
+        let mut graph = SyntheticGraph::new(1);
+        graph.erdos_renyi(1000, 0.01);
+        let stats = graph_stats(&graph.to_matrix().0);
+        assert_eq!(stats.peers, 1000);
+        assert_eq!(stats.self_loops, 0);
+        // About n * (n - 1) * p edges
+        assert!((9000..11000).contains(&stats.edges));
+        assert_eq!(graph.to_csv(), {
+            let mut again = SyntheticGraph::new(1);
+            again.erdos_renyi(1000, 0.01);
+            again.to_csv()
+        });
+
+        let mut graph = SyntheticGraph::new(1);
+        graph.barabasi_albert(2000, 3);
+        let stats = graph_stats(&graph.to_matrix().0);
+        assert_eq!(stats.edges, 12 + 1996 * 3 * 2);
+        assert_eq!(stats.components, 1);
+        // Hubs emerge
+        assert!(stats.in_degree.max > 50.0);
+
+        let mut graph = SyntheticGraph::new(1);
+        let blocks = graph.stochastic_block(&[100, 100], 0.2, 0.01);
+        let (inside, across): (Vec<&(usize, usize, f64)>, Vec<_>) = graph
+            .edges()
+            .iter()
+            .partition(|(from, to, _)| blocks[0].contains(from) == blocks[0].contains(to));
+        assert!(inside.len() > 10 * across.len());
+
+        let mut graph = SyntheticGraph::new(2);
+        let honest = graph.erdos_renyi(10, 1.0);
+        assert_eq!(graph.edge_count(), 90);
+        let ring = graph.collusion_ring(honest, 4, 2);
+        assert_eq!(ring, 10..14);
+        assert_eq!(graph.edge_count(), 90 + 4 + 2);
+        assert!(graph.to_csv().contains("ring13,ring10,1\n"));
+    }
+
+    #[test]
+    fn test_sybil_region() {
+        let mut graph = SyntheticGraph::new(3);
+        let honest = graph.barabasi_albert(300, 3);
+        let sybils = graph.sybil_region(honest.clone(), 300, 0.05, 3);
+        assert_eq!(sybils, 300..600);
+        assert!(graph.to_csv().contains("sybil599"));
+
+        let options = ComputeOptions {
+            alpha: 0.15,
+            ..ComputeOptions::default()
+        };
+        let scores =
+            calculate_from_csv_with_options(&graph.to_csv(), &graph.pretrust_csv(0..10), &options)
+                .unwrap();
+        let (sybils, honest): (Vec<_>, Vec<_>) = scores
+            .iter()
+            .partition(|(peer, _)| peer.starts_with("sybil"));
+        assert_eq!(honest.len(), 300);
+        assert!(!sybils.is_empty());
+        // Half the peers, but only a few edges in.
+        let sybil_trust: f64 = sybils.iter().map(|(_, score)| score).sum();
+        assert!(sybil_trust < 0.2);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod stats;
+pub mod paths;
+pub mod reachability;
+pub mod components;
+pub mod generate;
+pub mod collusion;
+pub mod community;
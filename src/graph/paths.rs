@@ -0,0 +1,210 @@
+use crate::basic::util::PeersMap;
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+// Best-first search stops after this many partial paths, returning the paths
+// found so far, so that dense graphs with deep limits cannot run away.
+const MAX_EXPANSIONS: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrustPath {
+    // Peer indices from the pretrusted peer to the target.
+    pub peers: Vec<usize>,
+    // Pretrust of the first peer times the canonicalized trust along the path.
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NamedTrustPath {
+    pub peers: Vec<String>,
+    pub weight: f64,
+}
+
+struct Candidate(TrustPath);
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Heavier paths first; ties go to shorter paths, then lower peer indices.
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .weight
+            .total_cmp(&other.0.weight)
+            .then_with(|| other.0.peers.len().cmp(&self.0.peers.len()))
+            .then_with(|| other.0.peers.cmp(&self.0.peers))
+    }
+}
+
+// distances_to returns the number of positive edges from each peer to the
+// target, or None if it is farther than max_depth.
+fn distances_to(local_trust: &CSRMatrix, target: usize, max_depth: usize) -> Vec<Option<usize>> {
+    let n = local_trust.cs_matrix.major_dim;
+    let mut trusters = vec![Vec::new(); n];
+    for (from, row) in local_trust.cs_matrix.entries.iter().enumerate() {
+        for e in row.iter().filter(|e| e.value > 0.0) {
+            trusters[e.index].push(from);
+        }
+    }
+
+    let mut distances = vec![None; n];
+    distances[target] = Some(0);
+    let mut queue = VecDeque::from([target]);
+    while let Some(peer) = queue.pop_front() {
+        let distance = distances[peer].unwrap_or(0);
+        if distance == max_depth {
+            continue;
+        }
+        for &truster in &trusters[peer] {
+            if distances[truster].is_none() {
+                distances[truster] = Some(distance + 1);
+                queue.push_back(truster);
+            }
+        }
+    }
+    distances
+}
+
+// top_trust_paths returns up to k simple paths of at most max_depth edges
+// from pretrusted peers to the target, highest weight first. local_trust is
+// taken as parsed: each edge is weighted by its share of the truster's
+// positive trust, and distrust is ignored. pre_trust should be canonicalized.
+//
+// Weights only shrink along a path, so a best-first search yields paths in
+// order of weight.
+pub fn top_trust_paths(
+    local_trust: &CSRMatrix,
+    pre_trust: &Vector,
+    target: usize,
+    k: usize,
+    max_depth: usize,
+) -> Vec<TrustPath> {
+    let n = local_trust.cs_matrix.major_dim;
+    if target >= n || k == 0 {
+        return vec![];
+    }
+
+    let row_sums: Vec<f64> = local_trust
+        .cs_matrix
+        .entries
+        .iter()
+        .map(|row| row.iter().map(|e| e.value).filter(|&v| v > 0.0).sum())
+        .collect();
+    let distances = distances_to(local_trust, target, max_depth);
+
+    let mut heap: BinaryHeap<Candidate> = pre_trust
+        .entries
+        .iter()
+        .filter(|e| e.value > 0.0 && e.index < n && distances[e.index].is_some())
+        .map(|e| {
+            Candidate(TrustPath {
+                peers: vec![e.index],
+                weight: e.value,
+            })
+        })
+        .collect();
+
+    let mut paths = vec![];
+    let mut expansions = 0;
+    while let Some(Candidate(path)) = heap.pop() {
+        let last = *path.peers.last().unwrap();
+        if last == target {
+            paths.push(path);
+            if paths.len() == k {
+                break;
+            }
+            continue;
+        }
+
+        expansions += 1;
+        if expansions > MAX_EXPANSIONS {
+            log::warn!("Stopped path search after {} expansions", MAX_EXPANSIONS);
+            break;
+        }
+
+        let depth = path.peers.len() - 1;
+        for e in &local_trust.cs_matrix.entries[last] {
+            let reachable = distances[e.index].is_some_and(|d| depth + 1 + d <= max_depth);
+            if e.value <= 0.0 || !reachable || path.peers.contains(&e.index) {
+                continue;
+            }
+            let mut peers = path.peers.clone();
+            peers.push(e.index);
+            heap.push(Candidate(TrustPath {
+                peers,
+                weight: path.weight * e.value / row_sums[last],
+            }));
+        }
+    }
+    paths
+}
+
+pub fn name_paths(paths: Vec<TrustPath>, peers: &PeersMap) -> Vec<NamedTrustPath> {
+    paths
+        .into_iter()
+        .map(|path| NamedTrustPath {
+            peers: path
+                .peers
+                .iter()
+                .map(|index| peers.map_reversed.get(index).cloned().unwrap_or_default())
+                .collect(),
+            weight: path.weight,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparse::entry::Entry;
+
+    #[test]
+    fn test_top_trust_paths() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, 0 -> 3, 4 -> 3, 1 -| 2
+        let local_trust = CSRMatrix::new(
+            5,
+            5,
+            vec![
+                (0, 1, 2.0),
+                (0, 2, 1.0),
+                (0, 3, 1.0),
+                (1, 3, 1.0),
+                (1, 2, -5.0),
+                (2, 3, 1.0),
+                (4, 3, 1.0),
+            ],
+        );
+        let pre_trust = Vector::new(
+            5,
+            vec![
+                Entry { index: 0, value: 0.8 },
+                Entry { index: 4, value: 0.2 },
+            ],
+        );
+
+        let paths = top_trust_paths(&local_trust, &pre_trust, 3, 10, 2);
+        let peers: Vec<&[usize]> = paths.iter().map(|p| p.peers.as_slice()).collect();
+        assert_eq!(peers, vec![&[0, 1, 3][..], &[0, 3], &[4, 3], &[0, 2, 3]]);
+        let weights: Vec<f64> = paths.iter().map(|p| p.weight).collect();
+        assert_eq!(weights, vec![0.4, 0.2, 0.2, 0.2]);
+
+        assert_eq!(top_trust_paths(&local_trust, &pre_trust, 3, 1, 1)[0].peers, vec![0, 3]);
+        assert_eq!(top_trust_paths(&local_trust, &pre_trust, 0, 3, 4)[0].peers, vec![0]);
+        assert_eq!(top_trust_paths(&local_trust, &pre_trust, 4, 3, 4)[0].peers, vec![4]);
+        assert!(top_trust_paths(&local_trust, &pre_trust, 2, 3, 0).is_empty());
+    }
+}
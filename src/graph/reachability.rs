@@ -0,0 +1,173 @@
+use crate::basic::util::PeersMap;
+use crate::sparse::entry::Entry;
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// reachable_from marks the peers that can be reached from the seeds along
+// positive local trust, including the seeds themselves.
+pub fn reachable_from(local_trust: &CSRMatrix, seeds: &[usize]) -> Vec<bool> {
+    let n = local_trust.cs_matrix.major_dim;
+    let mut reachable = vec![false; n];
+    let mut queue = VecDeque::new();
+    for &seed in seeds.iter().filter(|&&seed| seed < n) {
+        if !reachable[seed] {
+            reachable[seed] = true;
+            queue.push_back(seed);
+        }
+    }
+
+    while let Some(peer) = queue.pop_front() {
+        for e in &local_trust.cs_matrix.entries[peer] {
+            if e.value > 0.0 && e.index < n && !reachable[e.index] {
+                reachable[e.index] = true;
+                queue.push_back(e.index);
+            }
+        }
+    }
+    reachable
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Reachability {
+    pub peers: usize,
+    pub reachable: usize,
+    // Peers no pretrust can flow to; they always score zero.
+    pub unreachable: Vec<String>,
+    // Pretrusted peers that trust nobody, so their pretrust goes straight
+    // back to the other pretrusted peers.
+    pub disconnected_seeds: Vec<String>,
+}
+
+// reachability reports which peers of parsed local trust the pretrust reaches.
+pub fn reachability(local_trust: &CSRMatrix, pre_trust: &Vector, peers: &PeersMap) -> Reachability {
+    let seeds: Vec<usize> = pre_trust
+        .entries
+        .iter()
+        .filter(|e| e.value > 0.0)
+        .map(|e| e.index)
+        .collect();
+    let reachable = reachable_from(local_trust, &seeds);
+    let name = |index: usize| peers.map_reversed.get(&index).cloned().unwrap_or_default();
+
+    Reachability {
+        peers: reachable.len(),
+        reachable: reachable.iter().filter(|&&r| r).count(),
+        unreachable: (0..reachable.len()).filter(|&i| !reachable[i]).map(name).collect(),
+        disconnected_seeds: seeds
+            .into_iter()
+            .filter(|&seed| {
+                let row = &local_trust.cs_matrix.entries[seed];
+                !row.iter().any(|e| e.value > 0.0 && e.index != seed)
+            })
+            .map(name)
+            .collect(),
+    }
+}
+
+// select_peers keeps only the given peers' rows and columns, renumbered in
+// the order given.
+pub fn select_peers(matrix: &CSRMatrix, keep: &[usize]) -> CSRMatrix {
+    let n = matrix.cs_matrix.major_dim;
+    let mut new_index = vec![None; n.max(matrix.cs_matrix.minor_dim)];
+    for (i, &peer) in keep.iter().enumerate() {
+        new_index[peer] = Some(i);
+    }
+
+    let mut entries = vec![];
+    for (i, &peer) in keep.iter().enumerate() {
+        for e in &matrix.cs_matrix.entries[peer] {
+            if let Some(j) = new_index[e.index] {
+                entries.push((i, j, e.value));
+            }
+        }
+    }
+    CSRMatrix::new(keep.len(), keep.len(), entries)
+}
+
+// select_entries is select_peers for a vector.
+pub fn select_entries(v: &Vector, keep: &[usize]) -> Vector {
+    let mut new_index = vec![None; v.dim];
+    for (i, &peer) in keep.iter().enumerate() {
+        new_index[peer] = Some(i);
+    }
+    let entries = v
+        .entries
+        .iter()
+        .filter_map(|e| {
+            new_index[e.index].map(|index| Entry {
+                index,
+                value: e.value,
+            })
+        })
+        .collect();
+    Vector::new(keep.len(), entries)
+}
+
+// restore_peers undoes select_peers, back to dimension n.
+pub fn restore_peers(matrix: &CSRMatrix, keep: &[usize], n: usize) -> CSRMatrix {
+    let mut entries = vec![];
+    for (i, row) in matrix.cs_matrix.entries.iter().enumerate() {
+        for e in row {
+            entries.push((keep[i], keep[e.index], e.value));
+        }
+    }
+    CSRMatrix::new(n, n, entries)
+}
+
+// restore_entries undoes select_entries, back to dimension n.
+pub fn restore_entries(v: &Vector, keep: &[usize], n: usize) -> Vector {
+    let entries = v
+        .entries
+        .iter()
+        .map(|e| Entry {
+            index: keep[e.index],
+            value: e.value,
+        })
+        .collect();
+    Vector::new(n, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachability() {
+        let mut peers = PeersMap::new();
+        for name in ["alice", "bob", "carol", "dave", "erin"] {
+            peers.insert_or_get(name.to_string());
+        }
+        // alice -> bob, carol -> dave, dave -| bob, erin trusts only herself.
+        let local_trust = CSRMatrix::new(
+            5,
+            5,
+            vec![(0, 1, 1.0), (2, 3, 1.0), (3, 1, -1.0), (4, 4, 1.0)],
+        );
+        let pre_trust = Vector::new(
+            5,
+            vec![
+                Entry { index: 0, value: 0.5 },
+                Entry { index: 4, value: 0.5 },
+            ],
+        );
+
+        let report = reachability(&local_trust, &pre_trust, &peers);
+        assert_eq!(report.peers, 5);
+        assert_eq!(report.reachable, 3);
+        assert_eq!(report.unreachable, vec!["carol", "dave"]);
+        assert_eq!(report.disconnected_seeds, vec!["erin"]);
+
+        let keep = [0, 1, 4];
+        let selected = select_peers(&local_trust, &keep);
+        assert_eq!(selected.cs_matrix.nnz(), 2);
+        assert_eq!(
+            restore_peers(&selected, &keep, 5),
+            CSRMatrix::new(5, 5, vec![(0, 1, 1.0), (4, 4, 1.0)])
+        );
+        let selected = select_entries(&pre_trust, &keep);
+        assert_eq!(selected.entries[1].index, 2);
+        assert_eq!(restore_entries(&selected, &keep, 5), pre_trust);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::graph::components::{component_sizes, strongly_connected_components};
+use crate::sparse::matrix::CSRMatrix;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+// distribution summarizes the values, all zero if there are none.
+pub fn distribution(mut values: Vec<f64>) -> Distribution {
+    if values.is_empty() {
+        return Distribution::default();
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+
+    let n = values.len();
+    let median = if n.is_multiple_of(2) {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    } else {
+        values[n / 2]
+    };
+    // Nearest rank
+    let p90 = values[((0.9 * n as f64).ceil() as usize).max(1) - 1];
+
+    Distribution {
+        min: values[0],
+        mean: values.iter().sum::<f64>() / n as f64,
+        median,
+        p90,
+        max: values[n - 1],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphStats {
+    pub peers: usize,
+    pub edges: usize,
+    pub self_loops: usize,
+    pub negative_edges: usize,
+    // Peers that trust nobody; their rows are replaced by pretrust on canonicalization.
+    pub dangling_peers: usize,
+    // Peers that nobody trusts; they can only get pretrust.
+    pub unreferenced_peers: usize,
+    pub total_weight: f64,
+    pub min_weight: f64,
+    pub max_weight: f64,
+    pub mean_weight: f64,
+    pub median_weight: f64,
+    // Degrees count positive edges only.
+    pub in_degree: Distribution,
+    pub out_degree: Distribution,
+    // Strongly connected components along positive edges.
+    pub components: usize,
+    pub largest_component: usize,
+    pub singleton_components: usize,
+}
+
+// graph_stats summarizes a local trust matrix before canonicalization,
+// so negative (distrust) entries are still present.
+pub fn graph_stats(local_trust: &CSRMatrix) -> GraphStats {
+    let (n, _) = local_trust.dims();
+    let mut in_degree = vec![0usize; local_trust.cs_matrix.minor_dim];
+    let mut out_degree = vec![0usize; n];
+    let mut weights = vec![];
+    let mut stats = GraphStats {
+        peers: n,
+        edges: 0,
+        self_loops: 0,
+        negative_edges: 0,
+        dangling_peers: 0,
+        unreferenced_peers: 0,
+        total_weight: 0.0,
+        min_weight: 0.0,
+        max_weight: 0.0,
+        mean_weight: 0.0,
+        median_weight: 0.0,
+        in_degree: Distribution::default(),
+        out_degree: Distribution::default(),
+        components: 0,
+        largest_component: 0,
+        singleton_components: 0,
+    };
+
+    for (i, row) in local_trust.cs_matrix.entries.iter().enumerate() {
+        if row.iter().all(|e| e.value <= 0.0) {
+            stats.dangling_peers += 1;
+        }
+        for e in row {
+            if stats.edges == 0 {
+                stats.min_weight = e.value;
+                stats.max_weight = e.value;
+            }
+            stats.edges += 1;
+            stats.total_weight += e.value;
+            stats.min_weight = stats.min_weight.min(e.value);
+            stats.max_weight = stats.max_weight.max(e.value);
+            weights.push(e.value);
+            if e.index == i {
+                stats.self_loops += 1;
+            }
+            if e.value < 0.0 {
+                stats.negative_edges += 1;
+            } else {
+                in_degree[e.index] += 1;
+                out_degree[i] += 1;
+            }
+        }
+    }
+
+    stats.unreferenced_peers = in_degree.iter().filter(|&&d| d == 0).count();
+
+    let weights = distribution(weights);
+    stats.mean_weight = weights.mean;
+    stats.median_weight = weights.median;
+    stats.in_degree = distribution(in_degree.iter().map(|&d| d as f64).collect());
+    stats.out_degree = distribution(out_degree.iter().map(|&d| d as f64).collect());
+
+    let sizes = component_sizes(&strongly_connected_components(local_trust));
+    stats.components = sizes.len();
+    stats.largest_component = sizes.iter().cloned().max().unwrap_or(0);
+    stats.singleton_components = sizes.iter().filter(|&&size| size == 1).count();
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_stats() {
+        let m = CSRMatrix::new(
+            4,
+            4,
+            vec![(0, 1, 2.0), (0, 0, 1.0), (1, 2, 3.0), (2, 1, -1.0)],
+        );
+
+        let stats = graph_stats(&m);
+
+        assert_eq!(stats.peers, 4);
+        assert_eq!(stats.edges, 4);
+        assert_eq!(stats.self_loops, 1);
+        assert_eq!(stats.negative_edges, 1);
+        assert_eq!(stats.dangling_peers, 2);
+        assert_eq!(stats.unreferenced_peers, 1);
+        assert_eq!(stats.total_weight, 5.0);
+        assert_eq!(stats.min_weight, -1.0);
+        assert_eq!(stats.max_weight, 3.0);
+        assert_eq!(stats.mean_weight, 1.25);
+        assert_eq!(stats.median_weight, 1.5);
+        // in: 1, 1, 1, 0; out: 2, 1, 0, 0
+        assert_eq!(stats.in_degree.mean, 0.75);
+        assert_eq!(stats.in_degree.median, 1.0);
+        assert_eq!(stats.out_degree.p90, 2.0);
+        assert_eq!(stats.out_degree.median, 0.5);
+        assert_eq!(stats.components, 4);
+        assert_eq!(stats.largest_component, 1);
+        assert_eq!(stats.singleton_components, 4);
+
+        let m = CSRMatrix::new(3, 3, vec![(0, 1, 1.0), (1, 0, 1.0)]);
+        let stats = graph_stats(&m);
+        assert_eq!(stats.components, 2);
+        assert_eq!(stats.largest_component, 2);
+    }
+
+    #[test]
+    fn test_distribution() {
+        assert_eq!(distribution(vec![]), Distribution::default());
+        let d = distribution((1..=10).rev().map(|i| i as f64).collect());
+        assert_eq!(d.min, 1.0);
+        assert_eq!(d.mean, 5.5);
+        assert_eq!(d.median, 5.5);
+        assert_eq!(d.p90, 9.0);
+        assert_eq!(d.max, 10.0);
+    }
+}
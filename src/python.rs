@@ -0,0 +1,421 @@
+use crate::basic::engine::{
+    calculate_from_csv_with_options, calculate_from_matrix, ComputeOptions, TrustEngine,
+};
+use crate::basic::localtrust::{decay_local_trust, timestamped_local_trust, EdgeTimestamps};
+use crate::sparse::entry::Entry;
+use crate::sparse::matrix::CSRMatrix;
+use crate::sparse::vector::Vector;
+use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::fs;
+
+type Scores<'py> = (Vec<String>, Bound<'py, PyArray1<f64>>);
+
+fn value_error(e: String) -> PyErr {
+    PyValueError::new_err(e)
+}
+
+// decay is written like the CLI --decay, e.g. "exponential:30d".
+#[allow(clippy::too_many_arguments)]
+fn compute_options(
+    alpha: f64,
+    epsilon: Option<f64>,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+    distrust: &str,
+    algorithm: &str,
+    prune_unreachable: bool,
+    include_unreachable: bool,
+    decay: Option<&str>,
+    decay_reference: Option<f64>,
+) -> PyResult<ComputeOptions> {
+    Ok(ComputeOptions {
+        alpha,
+        epsilon,
+        max_iterations,
+        min_iterations,
+        distrust: distrust.parse().map_err(value_error)?,
+        algorithm: algorithm.parse().map_err(value_error)?,
+        prune_unreachable,
+        include_unreachable,
+        decay: decay.map(str::parse).transpose().map_err(value_error)?,
+        decay_reference,
+    })
+}
+
+// timestamp_array accepts timestamps in unix seconds like float_array, with
+// NaN (e.g. a missing value in pandas) for edges without one.
+fn timestamp_array(values: Option<&Bound<'_, PyAny>>, len: usize) -> PyResult<Vec<Option<f64>>> {
+    let timestamps = match values {
+        Some(values) => float_array(values)?,
+        None => return Ok(vec![None; len]),
+    };
+    if timestamps.len() != len {
+        return Err(value_error(format!(
+            "timestamps has {} entries, expected {}",
+            timestamps.len(),
+            len
+        )));
+    }
+    if let Some(t) = timestamps.iter().find(|t| t.is_infinite()) {
+        return Err(value_error(format!("Invalid timestamp: {}", t)));
+    }
+    Ok(timestamps.into_iter().map(|t| Some(t).filter(|t| !t.is_nan())).collect())
+}
+
+// float_array accepts a float64 NumPy array or any sequence of numbers,
+// such as a list or a pandas Series.
+fn float_array(values: &Bound<'_, PyAny>) -> PyResult<Vec<f64>> {
+    match values.extract::<PyReadonlyArray1<f64>>() {
+        Ok(array) => Ok(array.as_array().to_vec()),
+        Err(_) => values.extract(),
+    }
+}
+
+// index_array accepts int32 or int64 NumPy arrays, as used by scipy.sparse,
+// or any sequence of integers.
+fn index_array(values: &Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+    let values: Vec<i64> = if let Ok(array) = values.extract::<PyReadonlyArray1<i32>>() {
+        array.as_array().iter().map(|&i| i as i64).collect()
+    } else if let Ok(array) = values.extract::<PyReadonlyArray1<i64>>() {
+        array.as_array().to_vec()
+    } else {
+        values.extract()?
+    };
+    values
+        .into_iter()
+        .map(|i| usize::try_from(i).map_err(|_| value_error(format!("Invalid index: {}", i))))
+        .collect()
+}
+
+// peer_names converts every item of a sequence to its str().
+fn peer_names(peers: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    peers
+        .try_iter()?
+        .map(|peer| Ok(peer?.str()?.to_string()))
+        .collect()
+}
+
+fn split_scores(py: Python<'_>, scores: Vec<(String, f64)>) -> Scores<'_> {
+    let (peers, scores): (Vec<String>, Vec<f64>) = scores.into_iter().unzip();
+    (peers, scores.into_pyarray(py))
+}
+
+// compute_csv reads local trust and pretrust CSV files like the CLI and
+// returns (peers, scores) sorted by descending score.
+#[pyfunction]
+#[pyo3(signature = (localtrust_path, pretrust_path, *, alpha = 0.5, epsilon = None, max_iterations = None, min_iterations = None, distrust = "ignore", algorithm = "eigentrust", prune_unreachable = false, include_unreachable = false, decay = None, decay_reference = None))]
+#[allow(clippy::too_many_arguments)]
+fn compute_csv<'py>(
+    py: Python<'py>,
+    localtrust_path: &str,
+    pretrust_path: &str,
+    alpha: f64,
+    epsilon: Option<f64>,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+    distrust: &str,
+    algorithm: &str,
+    prune_unreachable: bool,
+    include_unreachable: bool,
+    decay: Option<&str>,
+    decay_reference: Option<f64>,
+) -> PyResult<Scores<'py>> {
+    let options = compute_options(
+        alpha,
+        epsilon,
+        max_iterations,
+        min_iterations,
+        distrust,
+        algorithm,
+        prune_unreachable,
+        include_unreachable,
+        decay,
+        decay_reference,
+    )?;
+    let localtrust_csv = fs::read_to_string(localtrust_path)?;
+    let pretrust_csv = fs::read_to_string(pretrust_path)?;
+
+    let scores = py
+        .detach(|| calculate_from_csv_with_options(&localtrust_csv, &pretrust_csv, &options))
+        .map_err(value_error)?;
+    Ok(split_scores(py, scores))
+}
+
+// compute_edges takes the local trust as parallel (source, target, weight)
+// columns, e.g. from a pandas DataFrame, and pretrust as peers with optional
+// weights (default 1). Peers are matched by their str(). Returns (peers, scores)
+// sorted by descending score. Decay needs the timestamps column.
+#[pyfunction]
+#[pyo3(signature = (sources, targets, weights, pretrust_peers, pretrust_weights = None, *, timestamps = None, alpha = 0.5, epsilon = None, max_iterations = None, min_iterations = None, distrust = "ignore", algorithm = "eigentrust", prune_unreachable = false, include_unreachable = false, decay = None, decay_reference = None))]
+#[allow(clippy::too_many_arguments)]
+fn compute_edges<'py>(
+    py: Python<'py>,
+    sources: &Bound<'py, PyAny>,
+    targets: &Bound<'py, PyAny>,
+    weights: &Bound<'py, PyAny>,
+    pretrust_peers: &Bound<'py, PyAny>,
+    pretrust_weights: Option<&Bound<'py, PyAny>>,
+    timestamps: Option<&Bound<'py, PyAny>>,
+    alpha: f64,
+    epsilon: Option<f64>,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+    distrust: &str,
+    algorithm: &str,
+    prune_unreachable: bool,
+    include_unreachable: bool,
+    decay: Option<&str>,
+    decay_reference: Option<f64>,
+) -> PyResult<Scores<'py>> {
+    let options = compute_options(
+        alpha,
+        epsilon,
+        max_iterations,
+        min_iterations,
+        distrust,
+        algorithm,
+        prune_unreachable,
+        include_unreachable,
+        decay,
+        decay_reference,
+    )?;
+    let sources = peer_names(sources)?;
+    let targets = peer_names(targets)?;
+    let weights = float_array(weights)?;
+    if sources.len() != targets.len() || sources.len() != weights.len() {
+        return Err(value_error(
+            "sources, targets and weights must have the same length".to_string(),
+        ));
+    }
+    let timestamps = timestamp_array(timestamps, sources.len())?;
+
+    let pretrust_peers = peer_names(pretrust_peers)?;
+    let pretrust_weights = match pretrust_weights {
+        Some(weights) => float_array(weights)?,
+        None => vec![1.0; pretrust_peers.len()],
+    };
+    if pretrust_peers.len() != pretrust_weights.len() {
+        return Err(value_error(
+            "pretrust_peers and pretrust_weights must have the same length".to_string(),
+        ));
+    }
+
+    let scores = py
+        .detach(|| {
+            let edges = sources.into_iter().zip(targets).zip(weights).zip(timestamps);
+            let mut engine = TrustEngine::from_timestamped_edges(
+                edges.map(|(((from, to), level), timestamp)| (from, to, level, timestamp)),
+            )?;
+            engine.compute_with_pretrust(
+                pretrust_peers.into_iter().zip(pretrust_weights),
+                &options,
+                None,
+            )
+        })
+        .map_err(value_error)?;
+    Ok(split_scores(py, scores))
+}
+
+// compute_csr takes a square local trust matrix as scipy.sparse CSR arrays
+// (indptr, indices, data) and a dense pretrust vector, and returns the dense
+// score vector in peer index order. Decay needs timestamps parallel to data.
+#[pyfunction]
+#[pyo3(signature = (indptr, indices, data, pretrust, *, timestamps = None, alpha = 0.5, epsilon = None, max_iterations = None, min_iterations = None, distrust = "ignore", algorithm = "eigentrust", prune_unreachable = false, include_unreachable = false, decay = None, decay_reference = None))]
+#[allow(clippy::too_many_arguments)]
+fn compute_csr<'py>(
+    py: Python<'py>,
+    indptr: &Bound<'py, PyAny>,
+    indices: &Bound<'py, PyAny>,
+    data: &Bound<'py, PyAny>,
+    pretrust: &Bound<'py, PyAny>,
+    timestamps: Option<&Bound<'py, PyAny>>,
+    alpha: f64,
+    epsilon: Option<f64>,
+    max_iterations: Option<usize>,
+    min_iterations: Option<usize>,
+    distrust: &str,
+    algorithm: &str,
+    prune_unreachable: bool,
+    include_unreachable: bool,
+    decay: Option<&str>,
+    decay_reference: Option<f64>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let options = compute_options(
+        alpha,
+        epsilon,
+        max_iterations,
+        min_iterations,
+        distrust,
+        algorithm,
+        prune_unreachable,
+        include_unreachable,
+        decay,
+        decay_reference,
+    )?;
+    let indptr = index_array(indptr)?;
+    let indices = index_array(indices)?;
+    let data = float_array(data)?;
+    let pretrust = float_array(pretrust)?;
+    let timestamps = timestamp_array(timestamps, data.len())?;
+
+    let scores = py
+        .detach(|| {
+            let (local_trust, timestamps) = csr_matrix(&indptr, &indices, &data, timestamps)?;
+            dense_scores(local_trust, &timestamps, &pretrust, options)
+        })
+        .map_err(value_error)?;
+    Ok(scores.into_pyarray(py))
+}
+
+// dense_scores computes the scores of a CSR local trust matrix as a dense
+// vector in peer index order.
+fn dense_scores(
+    mut local_trust: CSRMatrix,
+    timestamps: &EdgeTimestamps,
+    pretrust: &[f64],
+    mut options: ComputeOptions,
+) -> Result<Vec<f64>, String> {
+    // calculate_from_matrix has no timestamps, so decay is applied here.
+    if let Some(decay) = options.decay.take() {
+        local_trust = decay_local_trust(&local_trust, timestamps, &decay, options.decay_reference)?;
+    }
+    let dim = local_trust.cs_matrix.major_dim;
+    if pretrust.len() != dim {
+        return Err(format!(
+            "pretrust has {} entries, expected {}",
+            pretrust.len(),
+            dim
+        ));
+    }
+    let entries = pretrust
+        .iter()
+        .enumerate()
+        .filter(|(_, &value)| value != 0.0)
+        .map(|(index, &value)| Entry { index, value })
+        .collect();
+
+    let (scores, _) = calculate_from_matrix(local_trust, Vector::new(dim, entries), &options, None)?;
+    let mut dense = vec![0.0; dim];
+    for e in &scores.entries {
+        dense[e.index] = e.value;
+    }
+    Ok(dense)
+}
+
+fn csr_matrix(
+    indptr: &[usize],
+    indices: &[usize],
+    data: &[f64],
+    timestamps: Vec<Option<f64>>,
+) -> Result<(CSRMatrix, EdgeTimestamps), String> {
+    let dim = indptr.len().saturating_sub(1);
+    if indptr.first() != Some(&0) || indptr.windows(2).any(|w| w[0] > w[1]) {
+        return Err("Invalid indptr".to_string());
+    }
+    if indices.len() != data.len() || indptr[dim] != indices.len() {
+        return Err("indptr, indices and data do not match".to_string());
+    }
+
+    let mut entries = Vec::with_capacity(data.len());
+    for row in 0..dim {
+        for i in indptr[row]..indptr[row + 1] {
+            if indices[i] >= dim {
+                return Err(format!("Column index {} out of range", indices[i]));
+            }
+            if !data[i].is_finite() {
+                return Err(format!("Invalid trust level: {}", data[i]));
+            }
+            entries.push((row, indices[i], data[i], timestamps[i]));
+        }
+    }
+    Ok(timestamped_local_trust(dim, entries))
+}
+
+#[pymodule]
+fn eigentrust(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compute_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_edges, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_csr, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::decay::Decay;
+
+    #[test]
+    fn test_csr_matrix() {
+        let (matrix, timestamps) = csr_matrix(
+            &[0, 2, 3, 3],
+            &[1, 2, 0],
+            &[1.0, 2.0, 3.0],
+            vec![Some(10.0), None, Some(30.0)],
+        )
+        .unwrap();
+        assert_eq!(matrix.dims(), (3, 3));
+        assert_eq!(
+            timestamps,
+            vec![vec![Some(10.0), None], vec![Some(30.0)], vec![]]
+        );
+
+        let invalid = [
+            (vec![], vec![], vec![], "Invalid indptr"),
+            (vec![1, 1], vec![0], vec![1.0], "Invalid indptr"),
+            (vec![0, 2, 1], vec![0, 1], vec![1.0, 1.0], "Invalid indptr"),
+            (vec![0, 1, 2], vec![0], vec![1.0], "indptr, indices and data do not match"),
+            (vec![0, 1, 1], vec![2], vec![1.0], "Column index 2 out of range"),
+            (vec![0, 1, 1], vec![1], vec![f64::NAN], "Invalid trust level: NaN"),
+        ];
+        for (indptr, indices, data, expected) in invalid {
+            let timestamps = vec![None; data.len()];
+            let err = csr_matrix(&indptr, &indices, &data, timestamps).unwrap_err();
+            assert_eq!(err, expected, "indptr {:?}", indptr);
+        }
+    }
+
+    #[test]
+    fn test_dense_scores_decay() {
+        // 0 trusts 1 with an old edge and 2 with a new one.
+        let indptr = [0, 2, 3, 4];
+        let indices = [1, 2, 0, 0];
+        let data = [1.0, 1.0, 1.0, 1.0];
+        let timestamps = vec![Some(0.0), Some(100.0), None, None];
+        let pretrust = [1.0, 0.0, 0.0];
+
+        let compute = |decay: Option<Decay>| {
+            let (matrix, timestamps) =
+                csr_matrix(&indptr, &indices, &data, timestamps.clone()).unwrap();
+            let options = ComputeOptions {
+                decay,
+                ..ComputeOptions::default()
+            };
+            dense_scores(matrix, &timestamps, &pretrust, options)
+        };
+
+        let scores = compute(None).unwrap();
+        assert_eq!(scores[1], scores[2]);
+
+        // Ages are measured from the newest timestamp, so only 0 -> 1 is cut off.
+        let scores = compute(Some(Decay::Cutoff { max_age: 50.0 })).unwrap();
+        assert_eq!(scores[1], 0.0);
+        assert!(scores[2] > 0.0);
+
+        let (matrix, timestamps) =
+            csr_matrix(&indptr, &indices, &data, vec![None; data.len()]).unwrap();
+        let options = ComputeOptions {
+            decay: Some(Decay::Cutoff { max_age: 50.0 }),
+            ..ComputeOptions::default()
+        };
+        assert_eq!(
+            dense_scores(matrix, &timestamps, &pretrust, options).unwrap_err(),
+            "Decay needs local trust with timestamps"
+        );
+
+        let (matrix, timestamps) = csr_matrix(&indptr, &indices, &data, vec![None; 4]).unwrap();
+        let err = dense_scores(matrix, &timestamps, &[1.0], ComputeOptions::default());
+        assert_eq!(err.unwrap_err(), "pretrust has 1 entries, expected 3");
+    }
+}
@@ -0,0 +1,585 @@
+pub mod store;
+
+use crate::basic::engine::{calculate_from_csv_with_timings, ComputeOptions};
+use crate::basic::jobs::{JobId, JobQueue, JobRequest, JobStatus};
+use crate::basic::localtrust::read_local_trust_from_csv;
+use crate::basic::output::{format_scores, parse_columns, rank_scores, OutputFormat, OutputOptions};
+use crate::basic::trustvector::read_trust_vector_from_csv;
+use crate::basic::util::strip_headers;
+use crate::graph::stats::graph_stats;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use store::Store;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, json!({ "error": message }))
+    }
+
+    fn not_found() -> Response {
+        Response::error(404, "Not found")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComputeRequest {
+    localtrust: String,
+    pretrust: String,
+    #[serde(default)]
+    options: ComputeOptions,
+    // Scores are stored under this ID, replacing previous ones; generated if omitted.
+    id: Option<String>,
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // from_str_radix alone would accept a sign, as in "%+1".
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                out.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+                i += 3;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn output_options(query: &HashMap<String, String>) -> Result<OutputOptions, String> {
+    let mut options = OutputOptions {
+        format: OutputFormat::Json,
+        header: true,
+        ..OutputOptions::default()
+    };
+    if let Some(format) = query.get("format") {
+        options.format = format.parse()?;
+    }
+    if let Some(columns) = query.get("columns") {
+        options.columns = parse_columns(columns)?;
+    }
+    if let Some(top) = query.get("top") {
+        options.top_n = Some(top.parse().map_err(|_| format!("Invalid top: {}", top))?);
+    }
+    if let Some(min_score) = query.get("min_score") {
+        options.min_score = Some(
+            min_score
+                .parse()
+                .map_err(|_| format!("Invalid min_score: {}", min_score))?,
+        );
+    }
+    Ok(options)
+}
+
+fn content_type(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Csv => "text/csv",
+        OutputFormat::Tsv => "text/tab-separated-values",
+        OutputFormat::Json => "application/json",
+        OutputFormat::Jsonl => "application/x-ndjson",
+    }
+}
+
+// Server routes REST requests against an in-memory Store:
+//
+//   GET    /health
+//   GET    /localtrust                  list IDs
+//   PUT    /localtrust/{id}             upload or replace a local trust CSV
+//   GET    /localtrust/{id}             graph statistics
+//   DELETE /localtrust/{id}
+//   GET    /pretrust                    list IDs
+//   PUT    /pretrust/{id}               upload or replace a pretrust CSV
+//   DELETE /pretrust/{id}
+//   POST   /compute                     {"localtrust", "pretrust", "options", "id"},
+//                                       returns the scores ID and phase timings
+//   GET    /scores                      list IDs
+//   GET    /scores/{id}                 ?format=&columns=&top=&min_score=
+//   GET    /scores/{id}/peers/{peer}    score and rank of one peer
+//   DELETE /scores/{id}
+//   GET    /jobs                        list job IDs
+//   POST   /jobs                        same body as /compute, runs in the background
+//   GET    /jobs/{id}                   queued/running/done/failed/cancelled
+//   GET    /jobs/{id}/scores            same query as /scores, once done
+//   POST   /jobs/{id}/cancel
+//   DELETE /jobs/{id}                   cancel if needed and forget
+#[derive(Clone)]
+pub struct Server {
+    store: Arc<Mutex<Store>>,
+    jobs: Arc<JobQueue>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::with_job_workers(2)
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_job_workers(job_workers: usize) -> Self {
+        Server {
+            store: Arc::default(),
+            jobs: Arc::new(JobQueue::new(job_workers)),
+        }
+    }
+
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> Response {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query = parse_query(query);
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+        match (method, segments.as_slice()) {
+            ("GET", ["health"]) => Response::json(200, json!({ "status": "ok" })),
+            ("GET", ["localtrust"]) => self.list(|s| s.local_trust.keys().cloned().collect()),
+            ("PUT", ["localtrust", id]) => self.put_local_trust(id, body),
+            ("GET", ["localtrust", id]) => self.local_trust_stats(id),
+            ("DELETE", ["localtrust", id]) => {
+                self.delete(|s| s.local_trust.remove(*id).is_some())
+            }
+            ("GET", ["pretrust"]) => self.list(|s| s.pre_trust.keys().cloned().collect()),
+            ("PUT", ["pretrust", id]) => self.put_pretrust(id, body),
+            ("DELETE", ["pretrust", id]) => self.delete(|s| s.pre_trust.remove(*id).is_some()),
+            ("POST", ["compute"]) => self.compute(body),
+            ("GET", ["scores"]) => self.list(|s| s.scores.keys().cloned().collect()),
+            ("GET", ["scores", id]) => self.scores(id, &query),
+            ("GET", ["scores", id, "peers", peer]) => self.peer_score(id, peer),
+            ("DELETE", ["scores", id]) => self.delete(|s| s.scores.remove(*id).is_some()),
+            ("GET", ["jobs"]) => Response::json(200, json!({ "ids": self.jobs.ids() })),
+            ("POST", ["jobs"]) => self.submit_job(body),
+            ("GET", ["jobs", id]) => match id.parse().ok().and_then(|id| self.jobs.status(id)) {
+                Some(status) => Response::json(200, json!(status)),
+                None => Response::not_found(),
+            },
+            ("GET", ["jobs", id, "scores"]) => self.job_scores(id, &query),
+            ("POST", ["jobs", id, "cancel"]) => self.job_action(id, |id| self.jobs.cancel(id)),
+            ("DELETE", ["jobs", id]) => self.job_action(id, |id| self.jobs.remove(id)),
+            _ => Response::not_found(),
+        }
+    }
+
+    fn list<F>(&self, ids: F) -> Response
+    where
+        F: Fn(&Store) -> Vec<String>,
+    {
+        let store = self.store.lock().unwrap();
+        Response::json(200, json!({ "ids": ids(&store) }))
+    }
+
+    fn delete<F>(&self, remove: F) -> Response
+    where
+        F: Fn(&mut Store) -> bool,
+    {
+        if remove(&mut self.store.lock().unwrap()) {
+            Response::json(200, json!({ "deleted": true }))
+        } else {
+            Response::not_found()
+        }
+    }
+
+    fn put_local_trust(&self, id: &str, body: &str) -> Response {
+        // Reject unparseable input at upload time rather than at compute time.
+        if let Err(e) = read_local_trust_from_csv(strip_headers(body)) {
+            return Response::error(400, &e);
+        }
+        self.store
+            .lock()
+            .unwrap()
+            .local_trust
+            .insert(id.to_string(), body.to_string());
+        Response::json(200, json!({ "id": id }))
+    }
+
+    fn put_pretrust(&self, id: &str, body: &str) -> Response {
+        // Peers are checked against the local trust at compute time; here
+        // every named peer is known, so only the format and levels are checked.
+        let csv = strip_headers(body);
+        let peers: HashMap<String, usize> = csv
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (line.split(',').next().unwrap_or("").to_string(), index))
+            .collect();
+        if let Err(e) = read_trust_vector_from_csv(csv, &peers) {
+            return Response::error(400, &e);
+        }
+        self.store
+            .lock()
+            .unwrap()
+            .pre_trust
+            .insert(id.to_string(), body.to_string());
+        Response::json(200, json!({ "id": id }))
+    }
+
+    fn local_trust_stats(&self, id: &str) -> Response {
+        let csv = match self.store.lock().unwrap().local_trust.get(id) {
+            Some(csv) => csv.clone(),
+            None => return Response::not_found(),
+        };
+        match read_local_trust_from_csv(strip_headers(&csv)) {
+            Ok((local_trust, _)) => Response::json(200, json!(graph_stats(&local_trust))),
+            Err(e) => Response::error(400, &e),
+        }
+    }
+
+    // compute_request parses a compute body and looks up its inputs. Inputs are
+    // copied out so that the store is not locked during the compute.
+    fn compute_request(&self, body: &str) -> Result<(ComputeRequest, String, String), Response> {
+        let request: ComputeRequest =
+            serde_json::from_str(body).map_err(|e| Response::error(400, &e.to_string()))?;
+
+        let store = self.store.lock().unwrap();
+        match (
+            store.local_trust.get(&request.localtrust),
+            store.pre_trust.get(&request.pretrust),
+        ) {
+            (Some(lt), Some(pt)) => {
+                let (lt, pt) = (lt.clone(), pt.clone());
+                Ok((request, lt, pt))
+            }
+            (None, _) => Err(Response::error(404, "Unknown local trust ID")),
+            (_, None) => Err(Response::error(404, "Unknown pretrust ID")),
+        }
+    }
+
+    fn compute(&self, body: &str) -> Response {
+        let (request, localtrust_csv, pretrust_csv) = match self.compute_request(body) {
+            Ok(inputs) => inputs,
+            Err(response) => return response,
+        };
+
+        let (scores, timings) = match calculate_from_csv_with_timings(
+            &localtrust_csv,
+            &pretrust_csv,
+            &request.options,
+            None,
+        ) {
+            Ok(result) => result,
+            Err(e) => return Response::error(400, &e),
+        };
+
+        let mut store = self.store.lock().unwrap();
+        let id = request.id.unwrap_or_else(|| store.next_scores_id());
+        let peers = scores.len();
+        store.scores.insert(id.clone(), scores);
+
+        Response::json(201, json!({ "id": id, "peers": peers, "timings": timings }))
+    }
+
+    fn scores(&self, id: &str, query: &HashMap<String, String>) -> Response {
+        let store = self.store.lock().unwrap();
+        match store.scores.get(id) {
+            Some(scores) => formatted_scores(scores, query),
+            None => Response::not_found(),
+        }
+    }
+
+    fn peer_score(&self, id: &str, peer: &str) -> Response {
+        let store = self.store.lock().unwrap();
+        let scores = match store.scores.get(id) {
+            Some(scores) => scores,
+            None => return Response::not_found(),
+        };
+
+        match rank_scores(scores).into_iter().find(|row| row.peer == peer) {
+            Some(row) => Response::json(200, json!(row)),
+            None => Response::error(404, "Unknown peer"),
+        }
+    }
+
+    fn submit_job(&self, body: &str) -> Response {
+        let (request, localtrust_csv, pretrust_csv) = match self.compute_request(body) {
+            Ok(inputs) => inputs,
+            Err(response) => return response,
+        };
+        if request.id.is_some() {
+            return Response::error(400, "Jobs do not take a scores ID");
+        }
+
+        let id = self.jobs.submit(JobRequest {
+            localtrust_csv,
+            pretrust_csv,
+            options: request.options,
+        });
+        Response::json(202, json!({ "id": id, "status": JobStatus::Queued }))
+    }
+
+    fn job_scores(&self, id: &str, query: &HashMap<String, String>) -> Response {
+        let id: JobId = match id.parse() {
+            Ok(id) => id,
+            Err(_) => return Response::not_found(),
+        };
+        match (self.jobs.status(id), self.jobs.result(id)) {
+            (_, Some(scores)) => formatted_scores(&scores, query),
+            (Some(status), None) => Response::json(
+                409,
+                json!({ "error": "Job has no scores", "status": status }),
+            ),
+            (None, None) => Response::not_found(),
+        }
+    }
+
+    fn job_action<F>(&self, id: &str, action: F) -> Response
+    where
+        F: Fn(JobId) -> bool,
+    {
+        let id: JobId = match id.parse() {
+            Ok(id) => id,
+            Err(_) => return Response::not_found(),
+        };
+        match self.jobs.status(id) {
+            None => Response::not_found(),
+            Some(status) if !action(id) => {
+                Response::json(409, json!({ "error": "Job already finished", "status": status }))
+            }
+            Some(_) => Response::json(200, json!({ "id": id, "status": self.jobs.status(id) })),
+        }
+    }
+
+    fn respond(&self, mut request: tiny_http::Request) {
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+            Err(e) => Response::error(400, &e.to_string()),
+        };
+
+        log::info!("{} {} {}", request.method(), request.url(), response.status);
+
+        let header = tiny_http::Header::from_bytes("Content-Type", response.content_type)
+            .expect("valid content type header");
+        let http_response = tiny_http::Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(header);
+        if let Err(e) = request.respond(http_response) {
+            log::warn!("Failed to send response: {}", e);
+        }
+    }
+}
+
+fn formatted_scores(scores: &[(String, f64)], query: &HashMap<String, String>) -> Response {
+    let options = match output_options(query) {
+        Ok(options) => options,
+        Err(e) => return Response::error(400, &e),
+    };
+
+    match format_scores(scores, &options) {
+        Ok(body) => Response {
+            status: 200,
+            content_type: content_type(options.format),
+            body,
+        },
+        Err(e) => Response::error(500, &e),
+    }
+}
+
+// serve binds addr and handles requests on the given number of threads, with
+// job_workers threads running background jobs. It only returns if binding fails.
+pub fn serve(addr: &str, threads: usize, job_workers: usize) -> Result<(), String> {
+    let http = tiny_http::Server::http(addr).map_err(|e| e.to_string())?;
+    log::info!("Listening on http://{}", http.server_addr());
+    run(Arc::new(http), Server::with_job_workers(job_workers), threads);
+    Ok(())
+}
+
+fn run(http: Arc<tiny_http::Server>, server: Server, threads: usize) {
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|_| {
+            let http = http.clone();
+            let server = server.clone();
+            thread::spawn(move || {
+                for request in http.incoming_requests() {
+                    server.respond(request);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn request(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            addr,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn test_handle() {
+        let server = Server::new();
+
+        let response = server.handle("PUT", "/localtrust/lt", "i,j,v\nalice,bob,1\nbob,alice,x");
+        assert_eq!(response.status, 400);
+
+        let response = server.handle("PUT", "/localtrust/lt", "alice,bob,1\nbob,carol,1\ncarol,alice,1");
+        assert_eq!(response.status, 200);
+        let response = server.handle("PUT", "/pretrust/pt", "peer,level\nalice,x");
+        assert_eq!(response.status, 400);
+        assert_eq!(server.handle("PUT", "/pretrust/pt", "peer,level\nalice,1").status, 200);
+
+        let response = server.handle(
+            "POST",
+            "/compute",
+            r#"{"localtrust": "lt", "pretrust": "missing"}"#,
+        );
+        assert_eq!(response.status, 404);
+
+        let response = server.handle(
+            "POST",
+            "/compute",
+            r#"{"localtrust": "lt", "pretrust": "pt", "options": {"alpha": 0.2}, "id": "run"}"#,
+        );
+        assert_eq!(response.status, 201);
+
+        let response = server.handle("GET", "/scores/run?top=1&columns=rank,score", "");
+        let value: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(value["scores"].as_array().unwrap().len(), 1);
+        assert_eq!(value["scores"][0]["rank"], 1);
+
+        let response = server.handle("GET", "/scores/run?format=csv&top=1", "");
+        assert_eq!(response.content_type, "text/csv");
+        assert!(response.body.starts_with("peer,score\n"));
+
+        let response = server.handle("GET", "/scores/run/peers/bob", "");
+        let value: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(value["peer"], "bob");
+
+        assert_eq!(server.handle("DELETE", "/scores/run", "").status, 200);
+        assert_eq!(server.handle("GET", "/scores/run", "").status, 404);
+    }
+
+    #[test]
+    fn test_jobs() {
+        let server = Server::with_job_workers(1);
+        server.handle("PUT", "/localtrust/lt", "alice,bob,1\nbob,carol,1\ncarol,alice,1");
+        server.handle("PUT", "/pretrust/pt", "alice,1");
+
+        let response = server.handle("POST", "/jobs", r#"{"localtrust": "lt", "pretrust": "pt"}"#);
+        assert_eq!(response.status, 202);
+        let value: Value = serde_json::from_str(&response.body).unwrap();
+        let id = value["id"].as_u64().unwrap();
+
+        assert_eq!(server.jobs.wait(id), Some(JobStatus::Done { peers: 3 }));
+        let response = server.handle("GET", &format!("/jobs/{}", id), "");
+        let value: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(value["state"], "done");
+
+        let response = server.handle("GET", &format!("/jobs/{}/scores?format=csv", id), "");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.lines().count(), 4);
+
+        assert_eq!(server.handle("POST", &format!("/jobs/{}/cancel", id), "").status, 409);
+        assert_eq!(server.handle("DELETE", &format!("/jobs/{}", id), "").status, 200);
+        assert_eq!(server.handle("GET", &format!("/jobs/{}", id), "").status, 404);
+        assert_eq!(server.handle("GET", "/jobs/x/scores", "").status, 404);
+    }
+
+    #[test]
+    fn test_serve_localhost() {
+        let http = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let addr = http.server_addr().to_ip().unwrap().to_string();
+        thread::spawn(move || run(http, Server::new(), 2));
+
+        let (status, _) = request(&addr, "PUT", "/localtrust/a%20b", "alice,bob,1\nbob,alice,1");
+        assert_eq!(status, 200);
+        let (status, _) = request(&addr, "PUT", "/pretrust/seeds", "alice,1");
+        assert_eq!(status, 200);
+
+        let (status, body) = request(
+            &addr,
+            "POST",
+            "/compute",
+            r#"{"localtrust": "a b", "pretrust": "seeds"}"#,
+        );
+        assert_eq!(status, 201);
+        let value: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["peers"], 2);
+
+        let (status, body) = request(&addr, "GET", &format!("/scores/{}", value["id"].as_str().unwrap()), "");
+        assert_eq!(status, 200);
+        let value: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["scores"][0]["peer"], "alice");
+
+        let (status, _) = request(&addr, "GET", "/nothing", "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b+c"), "a b+c");
+        assert_eq!(parse_query("peer=a+b&top=1")["peer"], "a b");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%+1x"), "%+1x");
+        assert_eq!(percent_decode("%41%4a"), "AJ");
+    }
+}
@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+// Store keeps uploaded inputs and computed scores in memory, keyed by ID,
+// like go-eigentrust's basic server. Inputs are kept as CSV text so that
+// computes go through the same pipeline as calculate_from_csv.
+#[derive(Debug, Default)]
+pub struct Store {
+    pub local_trust: BTreeMap<String, String>,
+    pub pre_trust: BTreeMap<String, String>,
+    pub scores: BTreeMap<String, Vec<(String, f64)>>,
+    next_scores_id: usize,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_scores_id(&mut self) -> String {
+        loop {
+            self.next_scores_id += 1;
+            let id = format!("scores-{}", self.next_scores_id);
+            if !self.scores.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+}
@@ -60,6 +60,7 @@ impl CSCEntriesSort {
     }
 }
 
+#[allow(clippy::unnecessary_sort_by)]
 pub fn sort_entries_by_index(entries: &mut [Entry]) {
     entries.sort_by(|a, b| a.index.cmp(&b.index));
 }
@@ -69,6 +70,7 @@ pub fn sort_entries_by_value(entries: &mut [Entry]) {
 }
 
 #[cfg(test)]
+#[allow(unused_mut, clippy::useless_vec)]
 mod tests {
     use super::*;
 
@@ -1,7 +1,5 @@
-use super::entry::{Entry, CooEntry};
+use super::entry::Entry;
 use super::vector::Vector;
-use std::cmp::Ordering;
-use std::ptr;
 
 
 #[derive(Clone, PartialEq, Debug)]
@@ -12,6 +10,7 @@ pub struct CSMatrix {
 }
 
 impl CSMatrix {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             major_dim: 0,
@@ -33,6 +32,7 @@ impl CSMatrix {
         Ok(self.major_dim)
     }
 
+    #[allow(clippy::extend_with_drain)]
     pub fn set_major_dim(&mut self, dim: usize) {
         if self.entries.capacity() < dim {
             let mut new_entries = Vec::with_capacity(dim);
@@ -130,6 +130,7 @@ pub struct CSRMatrix {
 }
 
 impl CSRMatrix {
+    #[allow(clippy::unnecessary_sort_by)]
     pub fn new(rows: usize, cols: usize, entries: Vec<(usize, usize, f64)>) -> Self {
         let mut matrix_entries = vec![Vec::new(); rows];
 
@@ -1,6 +1,3 @@
-use std::cmp::Ordering;
-use std::ops::Add;
-
 pub fn nil_if_empty<T>(slice: Vec<T>) -> Option<Vec<T>> {
     if slice.is_empty() {
         None
@@ -23,6 +20,7 @@ pub struct KBNSummer {
 }
 
 impl KBNSummer {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             sum: 0.0,
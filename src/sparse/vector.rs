@@ -1,9 +1,8 @@
 use rayon::prelude::*;
 use serde::Serialize;
 use std::cmp::Ordering;
-use std::sync::{Arc, Mutex};
 
-use super::entry::{CooEntry, Entry};
+use super::entry::Entry;
 use super::matrix::CSRMatrix;
 use super::util::KBNSummer;
 
@@ -71,34 +70,18 @@ impl Vector {
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn mul_vec(&mut self, m: &CSRMatrix, v1: &Self) -> Result<(), String> {
-        let dim = m.cs_matrix.dim()?;
-        if dim != v1.dim {
-            return Err("Dimension mismatch".to_string());
-        }
-
-        let entries: Vec<Entry> = (0..dim)
-            .into_par_iter()
-            .filter_map(|row| {
-                let product = vec_dot(&m.row_vector(row), v1);
-                if product != 0.0 {
-                    Some(Entry {
-                        index: row,
-                        value: product,
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        self.dim = dim;
-        self.entries = entries;
-
-        Ok(())
+        self.mul_vec_parallel(m, v1)
     }
 
+    // In the browser rayon can only run on a web worker pool started from JS
+    // (see src/threads.rs); without one the product is computed sequentially.
     #[cfg(target_arch = "wasm32")]
     pub fn mul_vec(&mut self, m: &CSRMatrix, v1: &Self) -> Result<(), String> {
+        #[cfg(feature = "wasm-threads")]
+        if crate::threads::pool_size() > 0 {
+            return self.mul_vec_parallel(m, v1);
+        }
+
         let dim = m.cs_matrix.dim()?;
         if dim != v1.dim {
             return Err("Dimension mismatch".to_string());
@@ -122,6 +105,34 @@ impl Vector {
         Ok(())
     }
 
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threads"))]
+    fn mul_vec_parallel(&mut self, m: &CSRMatrix, v1: &Self) -> Result<(), String> {
+        let dim = m.cs_matrix.dim()?;
+        if dim != v1.dim {
+            return Err("Dimension mismatch".to_string());
+        }
+
+        let entries: Vec<Entry> = (0..dim)
+            .into_par_iter()
+            .filter_map(|row| {
+                let product = vec_dot(&m.row_vector(row), v1);
+                if product != 0.0 {
+                    Some(Entry {
+                        index: row,
+                        value: product,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        self.dim = dim;
+        self.entries = entries;
+
+        Ok(())
+    }
+
     fn sort_entries_by_index(&mut self) {
         self.entries.sort_by_key(|e| e.index);
     }
@@ -0,0 +1,83 @@
+use rayon::{ThreadBuilder, ThreadPoolBuilder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use wasm_bindgen::prelude::*;
+
+// Rayon thread pool on web workers sharing this module's memory, in the style
+// of wasm-bindgen-rayon. threads.js starts the workers, which wait in
+// start_thread_pool_worker until build_thread_pool hands each of them a rayon
+// thread to run. Until the pool is built, computes take the sequential path.
+struct Channel {
+    sender: Mutex<Sender<ThreadBuilder>>,
+    receiver: Mutex<Receiver<ThreadBuilder>>,
+}
+
+static CHANNEL: OnceLock<Channel> = OnceLock::new();
+static POOL_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+fn channel() -> &'static Channel {
+    CHANNEL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        Channel {
+            sender: Mutex::new(sender),
+            receiver: Mutex::new(receiver),
+        }
+    })
+}
+
+// pool_size is the number of pool threads, or zero if there is no pool.
+pub fn pool_size() -> usize {
+    POOL_SIZE.load(Ordering::Acquire)
+}
+
+#[wasm_bindgen]
+pub fn thread_pool_size() -> usize {
+    pool_size()
+}
+
+#[wasm_bindgen]
+pub fn thread_pool_module() -> JsValue {
+    wasm_bindgen::module()
+}
+
+#[wasm_bindgen]
+pub fn thread_pool_memory() -> JsValue {
+    wasm_bindgen::memory()
+}
+
+// start_thread_pool_worker is called by each pool worker after instantiating
+// the module on the shared memory, and runs a rayon thread until the page closes.
+#[wasm_bindgen]
+pub fn start_thread_pool_worker() {
+    let thread = channel().receiver.lock().unwrap().recv();
+    match thread {
+        Ok(thread) => thread.run(),
+        Err(e) => log::error!("Thread pool worker stopped: {}", e),
+    }
+}
+
+// build_thread_pool hands num_threads started workers to rayon. It blocks
+// until they run, so it must be called from a worker, not the main thread.
+#[wasm_bindgen]
+pub fn build_thread_pool(num_threads: usize) -> Result<(), JsError> {
+    if num_threads == 0 {
+        return Err(JsError::new("Thread pool needs at least one thread"));
+    }
+
+    ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .spawn_handler(|thread| {
+            channel()
+                .sender
+                .lock()
+                .unwrap()
+                .send(thread)
+                .map_err(|e| std::io::Error::other(e.to_string()))
+        })
+        .build_global()?;
+
+    POOL_SIZE.store(num_threads, Ordering::Release);
+    log::info!("Thread pool started with {} threads", num_threads);
+    Ok(())
+}
@@ -0,0 +1,575 @@
+use crate::basic::eigentrust::{ComputeObserver, ComputeProgress};
+use crate::basic::engine::{
+    calculate_from_csv, calculate_from_csv_with_observer, ComputeOptions, TrustEngine,
+};
+use crate::basic::output::{
+    filter_rows, format_scores, rank_scores, row_to_json, Column, OutputFormat, OutputOptions,
+};
+use crate::graph::stats::GraphStats;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::basic::util::init_logger;
+use std::collections::HashMap;
+use std::panic;
+use std::str;
+
+#[wasm_bindgen(start)]
+fn main() {
+    panic::set_hook(Box::new(console_error_panic_hook::hook));
+    init_logger(None);
+    log::info!("WASM Eigentrust connected");
+}
+
+// set_log_level changes the console log level: off, error, warn, info, debug or trace.
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let level = level
+        .parse::<log::LevelFilter>()
+        .map_err(|_| format!("Invalid log level: {}", level))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+// run returns a serialized Result, {"Ok": [[peer, score], ...]} or {"Err": message}.
+// New code should use compute, which returns JS values and throws on errors.
+#[wasm_bindgen]
+pub fn run(localtrust_csv: &[u8], pretrust_csv: &[u8], alpha: f64) -> String {
+    let result = match (str::from_utf8(localtrust_csv), str::from_utf8(pretrust_csv)) {
+        (Ok(lt), Ok(pt)) => calculate_from_csv(lt, pt, Some(alpha)),
+        (Err(e), _) | (_, Err(e)) => Err(e.to_string()),
+    };
+    serde_json::to_string(&result).unwrap_or_else(|e| e.to_string())
+}
+
+// run_formatted accepts output options as JSON, e.g.
+// {"format": "csv", "columns": ["rank", "score"], "top_n": 100}
+#[wasm_bindgen]
+pub fn run_formatted(
+    localtrust_csv: &[u8],
+    pretrust_csv: &[u8],
+    alpha: f64,
+    output_options_json: &str,
+) -> Result<String, JsError> {
+    let lt = str::from_utf8(localtrust_csv)?;
+    let pt = str::from_utf8(pretrust_csv)?;
+
+    let options: OutputOptions = if output_options_json.trim().is_empty() {
+        OutputOptions::default()
+    } else {
+        serde_json::from_str(output_options_json)?
+    };
+
+    let result = calculate_from_csv(lt, pt, Some(alpha)).map_err(|e| JsError::new(&e))?;
+    format_scores(&result, &options).map_err(|e| JsError::new(&e))
+}
+
+// JsObserver forwards compute progress to a JS callback, which receives
+// {iteration, delta, elapsed_ms} and can return false to stop the compute.
+// A callback that throws also stops it.
+struct JsObserver<'a> {
+    callback: &'a js_sys::Function,
+}
+
+impl ComputeObserver for JsObserver<'_> {
+    fn on_iteration(&self, progress: &ComputeProgress) -> bool {
+        let value = js_sys::Object::new();
+        let fields = [
+            ("iteration", progress.iteration as f64),
+            ("delta", progress.delta),
+            ("elapsed_ms", progress.elapsed_ms),
+        ];
+        for (key, field) in fields {
+            let _ = js_sys::Reflect::set(&value, &JsValue::from_str(key), &JsValue::from_f64(field));
+        }
+
+        match self.callback.call1(&JsValue::NULL, &value) {
+            Ok(result) => result.as_bool() != Some(false),
+            Err(e) => {
+                log::warn!("Progress callback failed: {:?}", e);
+                false
+            }
+        }
+    }
+}
+
+// run_with_progress is run calling on_progress after every iteration.
+// It returns the scores as JSON, or "Compute cancelled" if stopped.
+#[wasm_bindgen]
+pub fn run_with_progress(
+    localtrust_csv: &[u8],
+    pretrust_csv: &[u8],
+    alpha: f64,
+    on_progress: &js_sys::Function,
+) -> Result<String, JsError> {
+    let lt = str::from_utf8(localtrust_csv)?;
+    let pt = str::from_utf8(pretrust_csv)?;
+
+    let options = ComputeOptions {
+        alpha,
+        ..ComputeOptions::default()
+    };
+    let observer = JsObserver {
+        callback: on_progress,
+    };
+
+    let result = calculate_from_csv_with_observer(lt, pt, &options, Some(&observer))
+        .map_err(|e| JsError::new(&e))?;
+    Ok(serde_json::to_string(&result)?)
+}
+
+// Types for the generated .d.ts; the extern types below use them in place
+// of the any that JsValue would give.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** CSV text, or its UTF-8 bytes as a Uint8Array (e.g. a Node Buffer). */
+export type CsvInput = string | Uint8Array;
+
+export type ScoreColumn = "rank" | "score" | "percentile" | "scaled" | "log_score";
+
+/** Weighs local trust by the age of its timestamp column; durations are in seconds. */
+export type Decay =
+    | { model: "exponential"; half_life: number }
+    | { model: "linear"; window: number }
+    | { model: "cutoff"; max_age: number };
+
+export interface ComputeOptions {
+    alpha?: number;
+    epsilon?: number;
+    max_iterations?: number;
+    min_iterations?: number;
+    distrust?: "ignore" | "discount";
+    algorithm?: "eigentrust" | "pagerank" | "ppr" | "sybilrank";
+    prune_unreachable?: boolean;
+    include_unreachable?: boolean;
+    /** Needs the timestamp column of the local trust; an error without one. */
+    decay?: Decay;
+    /** Unix seconds that ages are measured from; the newest timestamp by default. */
+    decay_reference?: number;
+    output?: "objects" | "arrays" | "csv" | "tsv" | "json" | "jsonl";
+    columns?: ScoreColumn[];
+    header?: boolean;
+    top_n?: number;
+    min_score?: number;
+}
+
+export interface ComputeProgress {
+    iteration: number;
+    delta: number;
+    elapsed_ms: number;
+}
+
+/** Return false to stop the compute. */
+export type ProgressCallback = (progress: ComputeProgress) => boolean | void;
+
+/** A row of the default "objects" output; other columns are present if requested. */
+export interface ScoreRow {
+    peer: string;
+    rank?: number;
+    score?: number;
+    percentile?: number;
+    scaled?: number;
+    log_score?: number;
+}
+
+/** The "arrays" output. */
+export interface ScoreArrays {
+    peers: string[];
+    scores: Float64Array;
+}
+
+/** ScoreRow[] by default, ScoreArrays for "arrays", and a string for text formats. */
+export type ComputeResult = ScoreRow[] | ScoreArrays | string;
+
+export interface Contribution {
+    peer: string;
+    trust: number;
+    weight: number;
+    contribution: number;
+}
+
+/** score = pretrust + sum of contributions - discount, up to convergence. */
+export interface Explanation {
+    peer: string;
+    score: number;
+    pretrust: number;
+    contributions: Contribution[];
+    discount: number;
+}
+
+/** Peers from a pretrusted peer to the target, with the product of pretrust and canonicalized trust along the way. */
+export interface TrustPath {
+    peers: string[];
+    weight: number;
+}
+
+export interface PhaseTimings {
+    parse_ms: number;
+    canonicalize_ms: number;
+    transpose_ms: number;
+    iterate_ms: number;
+    discount_ms: number;
+    sort_ms: number;
+    total_ms: number;
+}
+
+export interface Distribution {
+    min: number;
+    mean: number;
+    median: number;
+    p90: number;
+    max: number;
+}
+
+/** Structure of the local trust graph. Degrees and components only follow positive edges. */
+export interface GraphStats {
+    peers: number;
+    edges: number;
+    self_loops: number;
+    negative_edges: number;
+    dangling_peers: number;
+    unreferenced_peers: number;
+    total_weight: number;
+    min_weight: number;
+    max_weight: number;
+    mean_weight: number;
+    median_weight: number;
+    in_degree: Distribution;
+    out_degree: Distribution;
+    components: number;
+    largest_component: number;
+    singleton_components: number;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "CsvInput")]
+    pub type CsvInput;
+
+    #[wasm_bindgen(typescript_type = "ComputeOptions")]
+    pub type JsComputeOptions;
+
+    #[wasm_bindgen(typescript_type = "ProgressCallback")]
+    pub type ProgressCallback;
+
+    #[wasm_bindgen(typescript_type = "ComputeResult")]
+    pub type ComputeResult;
+
+    #[wasm_bindgen(typescript_type = "PhaseTimings | undefined")]
+    pub type JsPhaseTimings;
+
+    #[wasm_bindgen(typescript_type = "Explanation")]
+    pub type JsExplanation;
+
+    #[wasm_bindgen(typescript_type = "TrustPath[]")]
+    pub type JsTrustPaths;
+
+    #[wasm_bindgen(typescript_type = "GraphStats")]
+    pub type JsGraphStats;
+}
+
+// csv_input accepts a string or a Uint8Array of UTF-8, so callers don't need
+// TextDecoder or file paths.
+fn csv_input(input: &CsvInput, name: &str) -> Result<String, JsError> {
+    if let Some(text) = input.as_string() {
+        return Ok(text);
+    }
+    match input.dyn_ref::<js_sys::Uint8Array>() {
+        Some(bytes) => String::from_utf8(bytes.to_vec())
+            .map_err(|e| JsError::new(&format!("{} is not UTF-8: {}", name, e))),
+        None => Err(JsError::new(&format!("{} must be a string or Uint8Array", name))),
+    }
+}
+
+fn progress_callback(
+    on_progress: Option<ProgressCallback>,
+) -> Result<Option<js_sys::Function>, JsError> {
+    on_progress
+        .map(|callback| {
+            JsValue::from(callback)
+                .dyn_into::<js_sys::Function>()
+                .map_err(|_| JsError::new("on_progress must be a function"))
+        })
+        .transpose()
+}
+
+// ScoresOutput selects what compute returns: an array of {peer, rank, score}
+// objects, {peers: string[], scores: Float64Array}, or formatted text.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScoresOutput {
+    #[default]
+    Objects,
+    Arrays,
+    Csv,
+    Tsv,
+    Json,
+    Jsonl,
+}
+
+// JsOptions is the options object accepted by compute, e.g.
+// {alpha: 0.2, epsilon: 1e-9, max_iterations: 100, distrust: "discount",
+//  output: "objects", columns: ["rank", "score"], top_n: 10}
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsOptions {
+    #[serde(flatten)]
+    compute: ComputeOptions,
+    output: ScoresOutput,
+    columns: Option<Vec<Column>>,
+    header: Option<bool>,
+    top_n: Option<usize>,
+    min_score: Option<f64>,
+    // Keys neither struct knows; flatten would drop them silently otherwise.
+    #[serde(flatten)]
+    unknown: HashMap<String, serde_json::Value>,
+}
+
+impl JsOptions {
+    fn check_unknown(&self) -> Result<(), String> {
+        if self.unknown.is_empty() {
+            return Ok(());
+        }
+        let mut keys: Vec<&str> = self.unknown.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        Err(format!("Unknown option: {}", keys.join(", ")))
+    }
+
+    fn output_options(&self, format: OutputFormat) -> OutputOptions {
+        OutputOptions {
+            format,
+            columns: self
+                .columns
+                .clone()
+                .unwrap_or_else(|| vec![Column::Rank, Column::Score]),
+            header: self.header.unwrap_or(true),
+            top_n: self.top_n,
+            min_score: self.min_score,
+        }
+    }
+}
+
+fn js_options(options: Option<JsComputeOptions>) -> Result<JsOptions, JsError> {
+    let options: JsOptions = match options {
+        Some(options) => serde_wasm_bindgen::from_value(options.into())?,
+        None => JsOptions::default(),
+    };
+    options.check_unknown().map_err(|e| JsError::new(&e))?;
+    Ok(options)
+}
+
+fn scores_to_js(scores: &[(String, f64)], options: &JsOptions) -> Result<ComputeResult, JsError> {
+    let format = match options.output {
+        ScoresOutput::Objects | ScoresOutput::Arrays => OutputFormat::Json,
+        ScoresOutput::Csv => OutputFormat::Csv,
+        ScoresOutput::Tsv => OutputFormat::Tsv,
+        ScoresOutput::Json => OutputFormat::Json,
+        ScoresOutput::Jsonl => OutputFormat::Jsonl,
+    };
+    let output_options = options.output_options(format);
+
+    match options.output {
+        ScoresOutput::Objects => {
+            let rows: Vec<serde_json::Value> = filter_rows(rank_scores(scores), &output_options)
+                .iter()
+                .map(|row| row_to_json(row, &output_options.columns))
+                .collect();
+            let value = rows.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+            Ok(value.unchecked_into())
+        }
+        ScoresOutput::Arrays => {
+            let rows = filter_rows(rank_scores(scores), &output_options);
+            let peers: js_sys::Array = rows.iter().map(|row| JsValue::from_str(&row.peer)).collect();
+            let values: Vec<f64> = rows.iter().map(|row| row.score).collect();
+
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &JsValue::from_str("peers"), &peers)
+                .map_err(|_| JsError::new("Failed to build result"))?;
+            js_sys::Reflect::set(
+                &object,
+                &JsValue::from_str("scores"),
+                &js_sys::Float64Array::from(values.as_slice()),
+            )
+            .map_err(|_| JsError::new("Failed to build result"))?;
+            Ok(object.unchecked_into())
+        }
+        _ => format_scores(scores, &output_options)
+            .map(|text| JsValue::from_str(&text).unchecked_into())
+            .map_err(|e| JsError::new(&e)),
+    }
+}
+
+// compute runs EigenTrust on CSV strings or bytes and throws an Error on
+// failure. options may be undefined; on_progress is as in run_with_progress.
+#[wasm_bindgen]
+pub fn compute(
+    localtrust_csv: &CsvInput,
+    pretrust_csv: &CsvInput,
+    options: Option<JsComputeOptions>,
+    on_progress: Option<ProgressCallback>,
+) -> Result<ComputeResult, JsError> {
+    let localtrust_csv = csv_input(localtrust_csv, "localtrust")?;
+    let pretrust_csv = csv_input(pretrust_csv, "pretrust")?;
+    let options = js_options(options)?;
+    let on_progress = progress_callback(on_progress)?;
+
+    let observer = on_progress.as_ref().map(|callback| JsObserver { callback });
+    let scores = calculate_from_csv_with_observer(
+        &localtrust_csv,
+        &pretrust_csv,
+        &options.compute,
+        observer.as_ref().map(|observer| observer as &dyn ComputeObserver),
+    )
+    .map_err(|e| JsError::new(&e))?;
+
+    scores_to_js(&scores, &options)
+}
+
+fn stats_to_js(stats: &GraphStats) -> Result<JsGraphStats, JsError> {
+    let value = stats.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+    Ok(value.unchecked_into())
+}
+
+// stats describes the structure of local trust without computing scores.
+#[wasm_bindgen]
+pub fn stats(localtrust_csv: &CsvInput) -> Result<JsGraphStats, JsError> {
+    let localtrust_csv = csv_input(localtrust_csv, "localtrust")?;
+    let engine = TrustEngine::from_csv(&localtrust_csv).map_err(|e| JsError::new(&e))?;
+    stats_to_js(&engine.stats())
+}
+
+// TrustEngine parses local trust once and computes repeatedly with different
+// pretrust and options. It keeps the timestamp column, so the decay option
+// applies to each compute as it does to the compute function:
+//
+//   const engine = new TrustEngine(localtrust)
+//   engine.compute(pretrust, { alpha: 0.3, decay: { model: "cutoff", max_age: 7776000 } })
+//   engine.set_edge('alice', 'dave', 2)
+//   engine.score('dave')  // undefined until the next compute
+#[wasm_bindgen(js_name = TrustEngine)]
+pub struct WasmTrustEngine {
+    engine: TrustEngine,
+}
+
+#[wasm_bindgen(js_class = TrustEngine)]
+impl WasmTrustEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(localtrust_csv: &CsvInput) -> Result<WasmTrustEngine, JsError> {
+        let localtrust_csv = csv_input(localtrust_csv, "localtrust")?;
+        let engine = TrustEngine::from_csv(&localtrust_csv).map_err(|e| JsError::new(&e))?;
+        Ok(WasmTrustEngine { engine })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peers(&self) -> usize {
+        self.engine.peer_count()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn edges(&self) -> usize {
+        self.engine.edge_count()
+    }
+
+    // set_edge takes an optional timestamp in unix seconds, which the decay
+    // option weighs the edge by; edges without one are not decayed.
+    pub fn set_edge(
+        &mut self,
+        from: &str,
+        to: &str,
+        level: f64,
+        timestamp: Option<f64>,
+    ) -> Result<(), JsError> {
+        self.engine
+            .set_edge_at(from, to, level, timestamp)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    pub fn remove_edge(&mut self, from: &str, to: &str) -> bool {
+        self.engine.remove_edge(from, to)
+    }
+
+    // compute takes the same options and progress callback as the compute function.
+    pub fn compute(
+        &mut self,
+        pretrust_csv: &CsvInput,
+        options: Option<JsComputeOptions>,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<ComputeResult, JsError> {
+        let pretrust_csv = csv_input(pretrust_csv, "pretrust")?;
+        let options = js_options(options)?;
+        let on_progress = progress_callback(on_progress)?;
+
+        let observer = on_progress.as_ref().map(|callback| JsObserver { callback });
+        let scores = self
+            .engine
+            .compute_with_observer(
+                &pretrust_csv,
+                &options.compute,
+                observer.as_ref().map(|observer| observer as &dyn ComputeObserver),
+            )
+            .map_err(|e| JsError::new(&e))?;
+
+        scores_to_js(&scores, &options)
+    }
+
+    // score returns a peer's score from the last compute.
+    pub fn score(&self, peer: &str) -> Option<f64> {
+        self.engine.score(peer)
+    }
+
+    // explain breaks down a peer's score from the last compute into its
+    // pretrust share and the contributions of the peers that trust it.
+    pub fn explain(&self, peer: &str) -> Result<JsExplanation, JsError> {
+        let explanation = self.engine.explain(peer).map_err(|e| JsError::new(&e))?;
+        let value = explanation.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+        Ok(value.unchecked_into())
+    }
+
+    // trust_paths returns the k highest weight paths of at most max_depth
+    // edges from the pretrusted peers of the last compute to the peer.
+    pub fn trust_paths(
+        &self,
+        peer: &str,
+        k: usize,
+        max_depth: usize,
+    ) -> Result<JsTrustPaths, JsError> {
+        let paths = self
+            .engine
+            .trust_paths(peer, k, max_depth)
+            .map_err(|e| JsError::new(&e))?;
+        let value = paths.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+        Ok(value.unchecked_into())
+    }
+
+    pub fn stats(&self) -> Result<JsGraphStats, JsError> {
+        stats_to_js(&self.engine.stats())
+    }
+
+    // timings are the phase timings of the last compute, e.g. {iterate_ms: 12.5, ...}.
+    #[wasm_bindgen(getter)]
+    pub fn timings(&self) -> Result<JsPhaseTimings, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.engine.timings())?.unchecked_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_options_unknown_keys() {
+        let options: JsOptions =
+            serde_json::from_str(r#"{"alpha": 0.2, "output": "csv", "top_n": 3}"#).unwrap();
+        assert!(options.check_unknown().is_ok());
+        assert_eq!(options.compute.alpha, 0.2);
+        assert_eq!(options.top_n, Some(3));
+
+        let options: JsOptions = serde_json::from_str(r#"{"alpah": 0.2, "topn": 3}"#).unwrap();
+        assert_eq!(options.compute.alpha, 0.5);
+        assert_eq!(
+            options.check_unknown().unwrap_err(),
+            "Unknown option: alpah, topn"
+        );
+    }
+}
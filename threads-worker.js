@@ -0,0 +1,9 @@
+// Thread pool worker started by threads.js: instantiates the module on the
+// shared memory, reports back, then runs a rayon thread.
+self.onmessage = async function (event) {
+    const { module, memory } = event.data
+    const pkg = await import('./pkg/eigentrust.js')
+    await pkg.default(module, memory)
+    self.postMessage(true)
+    pkg.start_thread_pool_worker()
+}
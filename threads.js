@@ -0,0 +1,25 @@
+// initThreadPool starts numThreads web workers sharing the memory of the
+// initialized wasm module `pkg` and builds the rayon thread pool on them.
+// Call it from a worker: building the pool blocks until the threads run.
+// It resolves to false, leaving computes sequential, if the module was built
+// without the wasm-threads feature or the page is not cross-origin isolated.
+export async function initThreadPool(pkg, numThreads) {
+    if (!pkg.build_thread_pool || typeof SharedArrayBuffer === 'undefined') {
+        return false
+    }
+    const module = pkg.thread_pool_module()
+    const memory = pkg.thread_pool_memory()
+    if (!(memory.buffer instanceof SharedArrayBuffer)) {
+        return false
+    }
+
+    await Promise.all(Array.from({ length: numThreads }, () => new Promise((resolve, reject) => {
+        const worker = new Worker(new URL('./threads-worker.js', import.meta.url), { type: 'module' })
+        worker.onmessage = () => resolve()
+        worker.onerror = reject
+        worker.postMessage({ module, memory })
+    })))
+
+    pkg.build_thread_pool(numThreads)
+    return true
+}
@@ -1,14 +1,27 @@
 let e
 
-(async () => {
+const ready = (async () => {
     e = await import('./pkg/eigentrust.js')
     await e.default()
+
+    // Uses the rayon thread pool when built with build-threads.sh, sequential otherwise.
+    const { initThreadPool } = await import('./threads.js')
+    if (await initThreadPool(e, navigator.hardwareConcurrency || 4)) {
+        console.log('eigentrust thread pool:', e.thread_pool_size())
+    }
 })()
 
 self.onmessage = async function (event) {
+    await ready
     const { localtrustBytes, pretrustBytes, alpha } = event.data
     console.time("eigentrust job")
-    const result = e.run(localtrustBytes, pretrustBytes, alpha)
+    try {
+        const result = e.compute(localtrustBytes, pretrustBytes, { alpha }, (progress) => {
+            self.postMessage({ type: 'progress', progress })
+        })
+        self.postMessage({ type: 'result', result })
+    } catch (err) {
+        self.postMessage({ type: 'error', message: err.message })
+    }
     console.timeEnd("eigentrust job")
-    self.postMessage(result)
 }